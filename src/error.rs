@@ -0,0 +1,48 @@
+use codemap::CodeMap;
+use codemap::Spanned;
+use std::fmt::Debug;
+
+// Render `err` rustc-style, with the offending source line and a caret under
+// its span
+pub fn render_error<T: Debug>(map: &CodeMap, err: &Spanned<T>) -> String {
+  let loc = map.look_up_span(err.span);
+  let line = loc.file.source_line(loc.begin.line);
+  let line_num = (loc.begin.line + 1).to_string();
+  let gutter = " ".repeat(line_num.len());
+
+  // spans over multiple lines are underlined to the end of the first one
+  let end = if loc.end.line == loc.begin.line {
+    loc.end.column
+  } else {
+    line.chars().count()
+  };
+  let width = if end > loc.begin.column {
+    end - loc.begin.column
+  } else {
+    1
+  };
+
+  let mut out = String::new();
+  out.push_str(&format!("error: {:?}\n", err.node));
+  out.push_str(&format!(
+    "{}--> {}:{}:{}\n",
+    gutter,
+    loc.file.name(),
+    loc.begin.line + 1,
+    loc.begin.column + 1
+  ));
+  out.push_str(&format!("{} |\n", gutter));
+  out.push_str(&format!("{} | {}\n", line_num, line));
+  out.push_str(&format!(
+    "{} | {}{}\n",
+    gutter,
+    " ".repeat(loc.begin.column),
+    "^".repeat(width)
+  ));
+
+  out
+}
+
+#[cfg(test)]
+#[path = "./tests/error.rs"]
+mod tests;
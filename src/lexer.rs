@@ -1,11 +1,12 @@
 use codemap::File;
+use codemap::Span;
 use codemap::Spanned;
 use self::Token::*;
-use std::iter::Enumerate;
+use std::collections::VecDeque;
 use std::iter::Peekable;
-use std::str::Chars;
+use std::str::CharIndices;
 
-type LexIter<'a> = Peekable<Enumerate<Chars<'a>>>;
+type LexIter<'a> = Peekable<CharIndices<'a>>;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
@@ -25,23 +26,29 @@ pub enum Token {
   Int(i64),
   Str(String),
   Name(String),
-  UnclosedStr(String),
 
   // Keywords
   Break,
   Catch,
   Continue,
+  Elif,
   Else,
+  Finally,
   For,
   Func,
   If,
   Import,
   In,
+  LogAnd,
+  LogNot,
+  LogOr,
   Loop,
   Pass,
   Return,
   Save,
   Table,
+  Unless,
+  Until,
   Var,
   While,
 
@@ -53,6 +60,7 @@ pub enum Token {
   Dot,  // .
   Meta, // ::
   Semi, // ;
+  Und,  // _
 
   // Braces
   Cul, // {
@@ -74,6 +82,9 @@ pub enum Token {
   Not, // !
   Or,  // |
   Pct, // %
+  Pipe, // |>
+  Shl, // <<
+  Shr, // >>
   Sub, // -
 
   // Comparisons
@@ -85,22 +96,100 @@ pub enum Token {
   Ne,  // !=
 }
 
-fn lex_number(it: &mut LexIter) -> Token {
+#[derive(Debug, Clone, PartialEq)]
+pub enum LexErrorKind {
+  UnclosedStr,
+  BadEscape,
+  BadCodepoint,
+  BadNumber,
+}
+
+pub type LexError = Spanned<LexErrorKind>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenCategory {
+  Structure,
+  Comment,
+  Literal,
+  Identifier,
+  Keyword,
+  Symbol,
+  Brace,
+  Operator,
+}
+
+impl Token {
+  pub fn category(&self) -> TokenCategory {
+    match *self {
+      EOF | Enter | Exit | Space | End | Tab => TokenCategory::Structure,
+
+      Comment(_) => TokenCategory::Comment,
+
+      Null | Bool(_) | Float(_) | Int(_) | Str(_) => TokenCategory::Literal,
+
+      Name(_) => TokenCategory::Identifier,
+
+      Break | Catch | Continue | Elif | Else | Finally | For | Func | If | Import | In | LogAnd
+      | LogNot | LogOr | Loop | Pass | Return | Save | Table | Unless | Until | Var
+      | While => TokenCategory::Keyword,
+
+      Arr | Ass | Col | Com | Dot | Meta | Semi | Und => TokenCategory::Symbol,
+
+      Cul | Cur | Pal | Par | Sql | Sqr => TokenCategory::Brace,
+
+      Add | And | At | Car | Div | Dol | Mul | Neg | Not | Or | Pct | Pipe | Shl | Shr | Sub
+      | Eql | Ge | Gt | Le | Lt | Ne => TokenCategory::Operator,
+    }
+  }
+}
+
+// Lex an int or float literal. Underscores may separate digits, but only
+// with a digit on either side
+fn lex_number(it: &mut LexIter, input: &File) -> Result<Token, LexError> {
+  let start = lex_pos(it, input);
   let mut digits = String::new();
+  let mut valid = true;
+  let mut prev: Option<char> = None;
+
   while let Some(&(_i, c)) = it.peek() {
     match c {
-      '0'...'9' | '.' => {
+      '0'..='9' | '.' | '_' => {
         it.next();
-        digits.push(c);
+
+        match c {
+          '_' => valid &= prev.is_some_and(|p| p.is_ascii_digit()),
+          '.' => valid &= prev != Some('_') && !digits.contains('.'),
+          _ => (),
+        }
+
+        if c != '_' {
+          digits.push(c);
+        }
+
+        prev = Some(c);
       }
       _ => break,
     }
   }
 
-  match digits.contains(".") {
-    true => Float(digits.parse::<f64>().unwrap()),
-    false => Int(digits.parse::<i64>().unwrap()),
-  }
+  // trailing underscores are never valid
+  valid &= prev != Some('_');
+
+  let tok = if !valid {
+    None
+  } else if digits.contains('.') {
+    digits.parse::<f64>().ok().map(Float)
+  } else {
+    digits.parse::<i64>().ok().map(Int)
+  };
+
+  tok.ok_or_else(|| {
+    let end = lex_pos(it, input);
+    Spanned {
+      node: LexErrorKind::BadNumber,
+      span: input.span.subspan(start as u64, end as u64),
+    }
+  })
 }
 
 fn lex_name(it: &mut LexIter) -> Token {
@@ -109,7 +198,7 @@ fn lex_name(it: &mut LexIter) -> Token {
 
   while let Some(&(_i, c)) = it.peek() {
     match c {
-      'a'...'z' | 'A'...'Z' | '0'...'9' | '_' => {
+      'a'..='z' | 'A'..='Z' | '0'..='9' | '_' => {
         it.next();
         name.push(c);
       }
@@ -125,20 +214,29 @@ fn lex_name(it: &mut LexIter) -> Token {
     "break" => Break,
     "catch" => Catch,
     "continue" => Continue,
+    "elif" => Elif,
     "else" => Else,
+    "finally" => Finally,
     "for" => For,
     "fn" => Func,
     "if" => If,
     "import" => Import,
     "in" => In,
+    "and" => LogAnd,
+    "not" => LogNot,
+    "or" => LogOr,
     "loop" => Loop,
     "pass" => Pass,
     "return" => Return,
     "save" => Save,
     "table" => Table,
+    "unless" => Unless,
+    "until" => Until,
     "var" => Var,
     "while" => While,
 
+    "_" => Und,
+
     _ => Name(name),
   }
 }
@@ -178,12 +276,19 @@ fn lex_indent(it: &mut LexIter) -> u64 {
 }
 
 fn lex_pair(next: char, solo: Token, pair: Token, it: &mut LexIter) -> Token {
+  lex_pairs(solo, vec![(next, pair)], it)
+}
+
+// Like `lex_pair`, but for characters that start more than one pair
+fn lex_pairs(solo: Token, pairs: Vec<(char, Token)>, it: &mut LexIter) -> Token {
   it.next();
 
   if let Some(&(_i, c)) = it.peek() {
-    if c == next {
-      it.next();
-      return pair;
+    for (next, pair) in pairs {
+      if c == next {
+        it.next();
+        return pair;
+      }
     }
   }
 
@@ -192,89 +297,243 @@ fn lex_pair(next: char, solo: Token, pair: Token, it: &mut LexIter) -> Token {
   solo
 }
 
-fn lex_string(it: &mut LexIter) -> Token {
+// Return the byte offset of the next character in `it`, or the end of `input`
+fn lex_pos(it: &mut LexIter, input: &File) -> usize {
+  if let Some(&(j, _)) = it.peek() {
+    j
+  } else {
+    input.source().len()
+  }
+}
+
+// Lex exactly `count` hex digits, or as many as there are up to `count` if
+// `exact` is false
+fn lex_hex(it: &mut LexIter, count: usize, exact: bool) -> Option<u32> {
+  let mut digits = String::new();
+  while digits.len() < count {
+    match it.peek() {
+      Some(&(_i, c)) if c.is_ascii_hexdigit() => {
+        it.next();
+        digits.push(c);
+      }
+      _ => break,
+    }
+  }
+
+  if digits.is_empty() || (exact && digits.len() < count) {
+    return None;
+  }
+
+  u32::from_str_radix(&digits, 16).ok()
+}
+
+// Lex the escape sequence following a backslash at `start`
+fn lex_escape(it: &mut LexIter, input: &File, start: usize) -> Result<char, LexError> {
+  let bad = |kind: LexErrorKind, it: &mut LexIter| {
+    let end = lex_pos(it, input);
+    Err(Spanned {
+      node: kind,
+      span: input.span.subspan(start as u64, end as u64),
+    })
+  };
+
+  let c = match it.next() {
+    Some((_i, c)) => c,
+    None => return bad(LexErrorKind::UnclosedStr, it),
+  };
+
+  match c {
+    'n' => Ok('\n'),
+    'r' => Ok('\r'),
+    't' => Ok('\t'),
+
+    // \xHH, which must be ASCII since a lone byte above 0x7F isn't UTF-8
+    'x' => match lex_hex(it, 2, true) {
+      Some(val) if val <= 0x7F => Ok(char::from(val as u8)),
+      _ => bad(LexErrorKind::BadEscape, it),
+    },
+
+    // \u{H..HHHHHH}
+    'u' => {
+      if let Some(&(_i, '{')) = it.peek() {
+        it.next();
+      } else {
+        return bad(LexErrorKind::BadEscape, it);
+      }
+
+      let val = lex_hex(it, 6, false);
+
+      if let Some(&(_i, '}')) = it.peek() {
+        it.next();
+      } else {
+        return bad(LexErrorKind::BadEscape, it);
+      }
+
+      match val.map(char::from_u32) {
+        Some(Some(c)) => Ok(c),
+        Some(None) => bad(LexErrorKind::BadCodepoint, it),
+        None => bad(LexErrorKind::BadEscape, it),
+      }
+    }
+
+    _ => Ok(c),
+  }
+}
+
+fn lex_string(it: &mut LexIter, input: &File) -> Result<Token, LexError> {
   let mut contents = String::new();
-  let mut escaped = false;
+  let start = lex_pos(it, input);
   it.next();
 
   loop {
-    if let Some(&(_i, c)) = it.peek() {
-      if escaped {
-        match c {
-          'n' => {
-            it.next();
-            contents.push('\n');
-          }
-          'r' => {
-            it.next();
-            contents.push('\r');
-          }
-          't' => {
-            it.next();
-            contents.push('\t');
-          }
-          _ => {
-            it.next();
-            contents.push(c);
-          }
+    if let Some(&(i, c)) = it.peek() {
+      match c {
+        '\'' => {
+          it.next();
+          break;
         }
-        escaped = false;
-      } else {
-        match c {
-          '\'' => {
-            it.next();
-            break;
-          }
-          '\\' => {
-            it.next();
-            escaped = true;
-          }
-          _ => {
-            it.next();
-            contents.push(c);
-          }
+        '\\' => {
+          it.next();
+          contents.push(lex_escape(it, input, i)?);
+        }
+        _ => {
+          it.next();
+          contents.push(c);
         }
       }
     } else {
-      // this is kind of an escape hatch because I didn't think through the
-      // lexer it hadn't occurred to me at the time that lexing a token could
-      // fail, so the lexer has no functionality to support failure. oh well.
-      return UnclosedStr(contents);
+      let end = input.source().len();
+      return Err(Spanned {
+        node: LexErrorKind::UnclosedStr,
+        span: input.span.subspan(start as u64, end as u64),
+      });
+    }
+  }
+
+  Ok(Str(contents))
+}
+
+// Return true if `it` is at the start of a raw string: `r'` or `r#'`
+fn is_raw_string(it: &LexIter) -> bool {
+  let mut ahead = it.clone();
+  ahead.next();
+
+  loop {
+    match ahead.next() {
+      Some((_i, '#')) => continue,
+      Some((_i, '\'')) => return true,
+      _ => return false,
+    }
+  }
+}
+
+// Lex a raw string, ie `r'...'` or `r#'...'#`, without processing escapes
+fn lex_raw_string(it: &mut LexIter, input: &File) -> Result<Token, LexError> {
+  let mut contents = String::new();
+  let start = lex_pos(it, input);
+  it.next();
+
+  let mut hashes = 0;
+  while let Some(&(_i, '#')) = it.peek() {
+    it.next();
+    hashes += 1;
+  }
+  it.next();
+
+  loop {
+    match it.next() {
+      Some((_i, '\'')) => {
+        // only a quote followed by the same number of hashes closes it
+        let mut ahead = it.clone();
+        let mut closing = 0;
+        while closing < hashes {
+          match ahead.next() {
+            Some((_i, '#')) => closing += 1,
+            _ => break,
+          }
+        }
+
+        if closing == hashes {
+          *it = ahead;
+          break;
+        }
+
+        contents.push('\'');
+      }
+      Some((_i, c)) => contents.push(c),
+      None => {
+        let end = input.source().len();
+        return Err(Spanned {
+          node: LexErrorKind::UnclosedStr,
+          span: input.span.subspan(start as u64, end as u64),
+        });
+      }
     }
   }
 
-  Str(contents)
+  Ok(Str(contents))
 }
 
-pub fn lex(input: &File) -> Vec<Spanned<Token>> {
-  let mut tokens: Vec<Spanned<Token>> = Vec::new();
-  let mut it: LexIter = input.source().chars().enumerate().peekable();
-  let mut indent_stack: Vec<u64> = Vec::new();
-  let mut current_indent: u64 = 0;
+// Lazily lexes a `File` one token at a time
+pub struct Tokens<'a> {
+  input: &'a File,
+  it: LexIter<'a>,
+  indent_stack: Vec<u64>,
+  current_indent: u64,
+  queue: VecDeque<Spanned<Token>>,
+  last: Option<Token>,
+  done: bool,
+}
 
-  // start at indentation 0
-  indent_stack.push(current_indent);
+impl<'a> Tokens<'a> {
+  pub fn new(input: &'a File) -> Tokens<'a> {
+    Tokens {
+      input,
+      it: input.source().char_indices().peekable(),
+      // start at indentation 0
+      indent_stack: vec![0],
+      current_indent: 0,
+      queue: VecDeque::new(),
+      last: None,
+      done: false,
+    }
+  }
 
-  while let Some(&(i, c)) = it.peek() {
-    let x = if current_indent < indent_stack[indent_stack.len() - 1] {
-      indent_stack.pop();
+  fn push(&mut self, node: Token, span: Span) {
+    self.last = Some(node.clone());
+    self.queue.push_back(Spanned { node, span });
+  }
+
+  // Lex a single token from the input, queueing whatever it emits
+  fn lex_next(&mut self) -> Result<(), LexError> {
+    let (i, c) = match self.it.peek() {
+      Some(&x) => x,
+      None => {
+        self.finish();
+        return Ok(());
+      }
+    };
+
+    let x = if self.current_indent < self.indent_stack[self.indent_stack.len() - 1] {
+      self.indent_stack.pop();
       Exit
     } else {
       match c {
-        '#' => lex_comment(&mut it),
-        'a'...'z' | 'A'...'Z' | '_' => lex_name(&mut it),
-        '0'...'9' => lex_number(&mut it),
+        '#' => lex_comment(&mut self.it),
+        'r' if is_raw_string(&self.it) => lex_raw_string(&mut self.it, self.input)?,
+        'a'..='z' | 'A'..='Z' | '_' => lex_name(&mut self.it),
+        '0'..='9' => lex_number(&mut self.it, self.input)?,
         '\n' => {
-          let indent = lex_indent(&mut it);
+          let indent = lex_indent(&mut self.it);
 
-          if let Some(&(_, c)) = it.peek() {
+          if let Some(&(_, c)) = self.it.peek() {
             match c {
               '\n' => Space,
               _ => {
-                current_indent = indent;
+                self.current_indent = indent;
                 // if this panics, there's a bug - indent_stack should always have a 0
-                if indent > indent_stack[indent_stack.len() - 1] {
-                  indent_stack.push(indent);
+                if indent > self.indent_stack[self.indent_stack.len() - 1] {
+                  self.indent_stack.push(indent);
                   Enter
                 } else {
                   End
@@ -286,109 +545,107 @@ pub fn lex(input: &File) -> Vec<Spanned<Token>> {
           }
         }
         '\t' => {
-          it.next();
+          self.it.next();
           Tab
         }
 
-        '\'' => lex_string(&mut it),
+        '\'' => lex_string(&mut self.it, self.input)?,
 
         // Compound
-        '-' => lex_pair('>', Sub, Arr, &mut it),
-        '<' => lex_pair('=', Lt, Le, &mut it),
-        '>' => lex_pair('=', Gt, Ge, &mut it),
-        '=' => lex_pair('=', Ass, Eql, &mut it),
-        '!' => lex_pair('=', Not, Ne, &mut it),
-        ':' => lex_pair(':', Col, Meta, &mut it),
+        '-' => lex_pair('>', Sub, Arr, &mut self.it),
+        '<' => lex_pairs(Lt, vec![('=', Le), ('<', Shl)], &mut self.it),
+        '>' => lex_pairs(Gt, vec![('=', Ge), ('>', Shr)], &mut self.it),
+        '=' => lex_pair('=', Ass, Eql, &mut self.it),
+        '!' => lex_pair('=', Not, Ne, &mut self.it),
+        ':' => lex_pair(':', Col, Meta, &mut self.it),
+        '|' => lex_pair('>', Or, Pipe, &mut self.it),
 
         // Symbols
         // -> Arr
         // = Ass
         // : Col
         ',' => {
-          it.next();
+          self.it.next();
           Com
         }
         '.' => {
-          it.next();
+          self.it.next();
           Dot
         }
         // :: Meta
         ';' => {
-          it.next();
+          self.it.next();
           Semi
         }
 
         // Braces
         '(' => {
-          it.next();
+          self.it.next();
           Pal
         }
         ')' => {
-          it.next();
+          self.it.next();
           Par
         }
         '[' => {
-          it.next();
+          self.it.next();
           Sql
         }
         ']' => {
-          it.next();
+          self.it.next();
           Sqr
         }
         '{' => {
-          it.next();
+          self.it.next();
           Cul
         }
         '}' => {
-          it.next();
+          self.it.next();
           Cur
         }
 
         // Operators
         '+' => {
-          it.next();
+          self.it.next();
           Add
         }
         '&' => {
-          it.next();
+          self.it.next();
           And
         }
         '@' => {
-          it.next();
+          self.it.next();
           At
         }
         '^' => {
-          it.next();
+          self.it.next();
           Car
         }
         '/' => {
-          it.next();
+          self.it.next();
           Div
         }
         '$' => {
-          it.next();
+          self.it.next();
           Dol
         }
         '*' => {
-          it.next();
+          self.it.next();
           Mul
         }
         '~' => {
-          it.next();
+          self.it.next();
           Neg
         }
         // ! Not
-        '|' => {
-          it.next();
-          Or
-        }
+        // | Or
         '%' => {
-          it.next();
+          self.it.next();
           Pct
         }
         // - Sub
         _ => {
-          it.next();
+          self.it.next();
           Space
         }
       }
@@ -396,12 +653,12 @@ pub fn lex(input: &File) -> Vec<Spanned<Token>> {
 
     // figure out what the span was for this token
     // either there's something we can peek, or the span is until EOF
-    let mut end_i = if let Some(&(j, _)) = it.peek() {
+    let end_i = if let Some(&(j, _)) = self.it.peek() {
       j
     } else {
-      input.source().len()
+      self.input.source().len()
     };
-    let span = input.span.subspan(i as u64, end_i as u64);
+    let span = self.input.span.subspan(i as u64, end_i as u64);
 
     match x {
       // don't emit tokens for spaces or comments
@@ -409,72 +666,83 @@ pub fn lex(input: &File) -> Vec<Spanned<Token>> {
       Comment(_) => (),
 
       // don't insert duplicate newlines, or file-leading newlines
-      End => match tokens.last().cloned() {
-        None => (),
-        Some(x) => {
-          if x.node != End {
-            tokens.push(Spanned {
-              node: End,
-              span: span,
-            });
-          }
-        }
+      End => match self.last {
+        Some(ref x) if *x != End => self.push(End, span),
+        _ => (),
       },
 
       // exit should always be followed by a End
       Exit => {
-        tokens.push(Spanned {
-          node: Exit,
-          span: span,
-        });
-        tokens.push(Spanned {
-          node: End,
-          span: span,
-        });
+        self.push(Exit, span);
+        self.push(End, span);
       }
 
       // emit everything else
-      _ => tokens.push(Spanned {
-        node: x,
-        span: span,
-      }),
+      _ => self.push(x, span),
     }
+
+    Ok(())
   }
 
-  // make a span for all closing tokens
-  let end = input.source().len() as u64;
-  let span = input.span.subspan(end, end);
+  // Queue the closing tokens at EOF
+  fn finish(&mut self) {
+    // make a span for all closing tokens
+    let end = self.input.source().len() as u64;
+    let span = self.input.span.subspan(end, end);
 
-  // sometimes a trailing newline goes missing before EOF
-  if let Some(x) = tokens.last().cloned() {
-    if x.node != End {
-      tokens.push(Spanned {
-        node: End,
-        span: span,
-      });
+    // sometimes a trailing newline goes missing before EOF
+    match self.last {
+      Some(ref x) if *x != End => self.push(End, span),
+      _ => (),
     }
+
+    // exit blocks that are open at EOF
+    while self.indent_stack.len() > 1 {
+      self.push(Exit, span);
+      self.push(End, span);
+      self.indent_stack.pop();
+    }
+
+    // push the EOF token
+    self.push(EOF, span);
+    self.done = true;
   }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+  type Item = Result<Spanned<Token>, LexError>;
 
-  // exit blocks that are open at EOF
-  while indent_stack.len() > 1 {
-    tokens.push(Spanned {
-      node: Exit,
-      span: span,
-    });
-    tokens.push(Spanned {
-      node: End,
-      span: span,
-    });
-    indent_stack.pop();
+  fn next(&mut self) -> Option<Self::Item> {
+    while self.queue.is_empty() {
+      if self.done {
+        return None;
+      }
+
+      if let Err(why) = self.lex_next() {
+        self.done = true;
+        return Some(Err(why));
+      }
+    }
+
+    self.queue.pop_front().map(Ok)
   }
+}
 
-  // push the EOF token
-  tokens.push(Spanned {
-    node: EOF,
-    span: span,
-  });
+pub fn lex(input: &File) -> Result<Vec<Spanned<Token>>, LexError> {
+  Tokens::new(input).collect()
+}
 
-  tokens
+// Lex `input` and pair each token with its category, for tools like syntax
+// highlighters that only care about the broad kind of each token
+pub fn lex_with_categories(input: &File) -> Result<Vec<(Spanned<Token>, TokenCategory)>, LexError> {
+  Tokens::new(input)
+    .map(|tok| {
+      tok.map(|tok| {
+        let cat = tok.node.category();
+        (tok, cat)
+      })
+    })
+    .collect()
 }
 
 #[cfg(test)]
@@ -1,4 +1,5 @@
 extern crate codemap;
+pub mod error;
 pub mod lexer;
 pub mod parser;
 pub mod semck;
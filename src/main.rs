@@ -5,6 +5,9 @@ extern crate mask;
 use clap::App;
 use clap::Arg;
 use codemap::CodeMap;
+use codemap::Spanned;
+use mask::error;
+use mask::lexer::LexErrorKind;
 use mask::lexer::Token;
 use mask::lexer;
 use mask::parser::ParseErrorKind;
@@ -16,7 +19,8 @@ use std::io::prelude::*;
 use std::io;
 use std::path::Path;
 
-fn print_tokens(map: &CodeMap, tokens: &Vec<codemap::Spanned<lexer::Token>>) {
+#[allow(dead_code)]
+fn print_tokens(map: &CodeMap, tokens: &[codemap::Spanned<lexer::Token>]) {
   let mut indent = 0;
 
   for token in tokens {
@@ -42,7 +46,7 @@ fn print_tokens(map: &CodeMap, tokens: &Vec<codemap::Spanned<lexer::Token>>) {
       }
 
       _ => {
-        let span = map.look_up_span(token.span);
+        let _span = map.look_up_span(token.span);
         print!("{:?} ", token.node);
         //print!("{}:{}:{}: {:?} ", span.file.name(), span.begin.line, span.begin.column, token.node);
         //print!("{:?}<{:?}> ", token.node, );
@@ -79,14 +83,20 @@ fn main() {
 
     // FIXME this code is duplicated a lot, but that's because there's no
     // "module" component in the compiler yet
-    let tokens = lexer::lex(&file);
+    let tokens = match lexer::lex(&file) {
+      Ok(tokens) => tokens,
+      Err(why) => panic!("Couldn't lex:\n{}", error::render_error(&map, &why)),
+    };
     let ast = parser::parse(tokens);
 
     match ast {
-      Ok(mut root) => {
-        {
-          let mut ck = semck::SemChecker::new();
-          ck.check(&mut root);
+      Ok(root) => {
+        let mut ck = semck::SemChecker::new();
+        if let Err(why) = ck.check(&root) {
+          panic!("Bad semck: {:?}", why);
+        }
+        for warning in ck.warnings() {
+          println!("Warning: {:?}", warning);
         }
         println!("Checked: {:?}", root);
       }
@@ -108,23 +118,28 @@ fn main() {
     };
 
     // see FIXME above
-    let tokens = lexer::lex(&cm_file);
-    let mut ast = match parser::parse(tokens) {
+    let tokens = match lexer::lex(&cm_file) {
+      Ok(tokens) => tokens,
+      Err(why) => panic!("Couldn't lex:\n{}", error::render_error(&map, &why)),
+    };
+    let ast = match parser::parse(tokens) {
       Ok(root) => root,
       Err(why) => panic!("Couldn't semck: {:?}", why),
     };
 
     let mut ck = semck::SemChecker::new();
-    match ck.check(&mut ast) {
-      Err(why) => panic!("Bad semck: {:?}", why),
-      _ => {}
+    if let Err(why) = ck.check(&ast) {
+      panic!("Bad semck: {:?}", why);
+    }
+    for warning in ck.warnings() {
+      println!("Warning: {:?}", warning);
     }
 
     println!("Checked: {:?}", ast);
   } else {
     // FIXME needs to handle multiline statements
     // initial idea is to request an extra line when the AST matches
-    // UnexpectedToken(End) | UnexpectedEOF
+    // an error found at End | UnexpectedEOF
     // and then concatenate it to the previous line(s)
     // FIXME ^ this is implemented, but isn't quite right
     // ie, if you enter `if` as your first line, it will never get it right
@@ -140,14 +155,14 @@ fn main() {
       } else {
         print!("> ");
       }
-      io::stdout().flush();
+      io::stdout().flush().unwrap();
 
       match io::stdin().read_line(&mut buffer) {
         Ok(nbytes) => {
           chunk.push_str(&buffer);
 
           if nbytes == 0 || chunk == "quit\n" {
-            println!("");
+            println!();
             break;
           }
 
@@ -157,12 +172,34 @@ fn main() {
 
           let file = map.add_file(String::from("_stdin"), chunk.clone());
 
-          let tokens = lexer::lex(&file);
+          let tokens = match lexer::lex(&file) {
+            Ok(tokens) => tokens,
+
+            // strings can span lines, so wait for the rest of it
+            Err(Spanned {
+              node: LexErrorKind::UnclosedStr,
+              ..
+            }) => {
+              wait_for_blank = true;
+              continue;
+            }
+
+            Err(why) => {
+              print!("{}", error::render_error(&map, &why));
+              chunk.clear();
+              wait_for_blank = false;
+              continue;
+            }
+          };
           let ast = parser::parse(tokens);
           match ast {
             // incomplete statement - say we're waiting for an empty line and then skip the rest
-            Err(ParseErrorKind::UnexpectedToken(Token::End))
-            | Err(ParseErrorKind::UnexpectedEOF) => {
+            Err(ref why) if why.found() == Some(&Token::End) => {
+              wait_for_blank = true;
+              continue;
+            }
+
+            Err(ParseErrorKind::UnexpectedEOF) => {
               wait_for_blank = true;
               continue;
             }
@@ -1,5 +1,7 @@
 use codemap::Spanned;
 use lexer::Token;
+use lexer::TokenCategory;
+use std::fmt::Debug;
 use std::iter::Peekable;
 use std::slice::Iter;
 use self::ParseErrorKind::*;
@@ -10,20 +12,26 @@ type Parse = Result<Node, ParseErrorKind>;
 #[derive(Debug, Clone, PartialEq)]
 pub enum Var {
   Single(String),
+  Discard,
   Multi(Vec<Var>),
+  Default(Box<Var>, Box<Node>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Place {
   Single(Box<Node>),
   Multi(Vec<Place>),
+  Default(Box<Place>, Box<Node>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Node {
   Block(Vec<Node>),
   Stmt(Box<Node>),
-  Catch(Vec<Node>),
+  Catch {
+    body: Vec<Node>,
+    finally: Option<Vec<Node>>,
+  },
   Assn {
     lhs: Place,
     rhs: Box<Node>,
@@ -53,8 +61,10 @@ pub enum Node {
     body: Vec<Node>,
   },
   Return(Option<Box<Node>>),
-  Break,
-  Continue,
+  Tuple(Vec<Node>),
+  Discard,
+  Break(usize),
+  Continue(usize),
   Expr,
   Pass,
   Index {
@@ -114,10 +124,428 @@ pub enum Op {
 #[derive(Debug, Clone, PartialEq)]
 pub enum ParseErrorKind {
   UnexpectedToken(Token),
+  Expected { expected: Vec<Token>, found: Token },
+  ReservedWord(Token),
   UnexpectedEOF,
   UnknownBinaryOperator,
   UnknownUnaryOperator,
   UnusedPlaces,
+  NonTrailingDefault,
+}
+
+impl ParseErrorKind {
+  // The token that caused the error, if there was one
+  pub fn found(&self) -> Option<&Token> {
+    match *self {
+      UnexpectedToken(ref found) | Expected { ref found, .. } | ReservedWord(ref found) => {
+        Some(found)
+      }
+      _ => None,
+    }
+  }
+}
+
+// Walks a `Node` tree. Implementors override `visit` to handle the variants
+// they care about and call `walk_node` to recurse into everything else.
+pub trait Visitor {
+  type Error;
+
+  fn visit(&mut self, node: &Node) -> Result<(), Self::Error> {
+    self.walk_node(node)
+  }
+
+  fn visit_place(&mut self, place: &Place) -> Result<(), Self::Error> {
+    self.walk_place(place)
+  }
+
+  fn visit_var(&mut self, var: &Var) -> Result<(), Self::Error> {
+    self.walk_var(var)
+  }
+
+  fn walk_node(&mut self, node: &Node) -> Result<(), Self::Error> {
+    match *node {
+      Node::Block(ref body)
+      | Node::Else { ref body }
+      | Node::Loop { ref body }
+      | Node::Func { ref body, .. } => {
+        self.walk_body(body)?;
+      }
+
+      Node::Catch {
+        ref body,
+        ref finally,
+      } => {
+        self.walk_body(body)?;
+        if let Some(ref finally) = *finally {
+          self.walk_body(finally)?;
+        }
+      }
+
+      Node::Stmt(ref val) | Node::Return(Some(ref val)) | Node::UnExpr { ref val, .. } => {
+        self.visit(val)?;
+      }
+
+      Node::Assn { ref lhs, ref rhs } => {
+        self.visit_place(lhs)?;
+        self.visit(rhs)?;
+      }
+
+      Node::If {
+        ref cond,
+        ref body,
+        ref els,
+      } => {
+        self.visit(cond)?;
+        self.walk_body(body)?;
+        if let Some(ref els) = *els {
+          self.visit(els)?;
+        }
+      }
+
+      Node::ElseIf { ref cond, ref body } => {
+        self.visit(cond)?;
+        self.walk_body(body)?;
+      }
+
+      Node::For {
+        ref decl,
+        ref expr,
+        ref body,
+      } => {
+        self.visit_var(decl)?;
+        self.visit(expr)?;
+        self.walk_body(body)?;
+      }
+
+      Node::While { ref expr, ref body } => {
+        self.visit(expr)?;
+        self.walk_body(body)?;
+      }
+
+      Node::Index { ref lhs, ref rhs } | Node::BinExpr { ref lhs, ref rhs, .. } => {
+        self.visit(lhs)?;
+        self.visit(rhs)?;
+      }
+
+      Node::Method {
+        ref owner,
+        ref method,
+        ref args,
+      } => {
+        self.visit(owner)?;
+        self.visit(method)?;
+        self.walk_body(args)?;
+      }
+
+      Node::Call { ref func, ref args } => {
+        self.visit(func)?;
+        self.walk_body(args)?;
+      }
+
+      Node::Tuple(ref vals) => {
+        self.walk_body(vals)?;
+      }
+
+      Node::Lambda { ref expr, .. } => {
+        self.visit(expr)?;
+      }
+
+      _ => {}
+    }
+
+    Ok(())
+  }
+
+  fn walk_place(&mut self, place: &Place) -> Result<(), Self::Error> {
+    match *place {
+      Place::Single(ref node) => self.visit(node),
+      Place::Multi(ref places) => {
+        for pl in places {
+          self.visit_place(pl)?;
+        }
+        Ok(())
+      }
+      Place::Default(ref place, ref default) => {
+        self.visit_place(place)?;
+        self.visit(default)
+      }
+    }
+  }
+
+  fn walk_var(&mut self, var: &Var) -> Result<(), Self::Error> {
+    match *var {
+      Var::Single(_) | Var::Discard => Ok(()),
+      Var::Multi(ref vars) => {
+        for var in vars {
+          self.visit_var(var)?;
+        }
+        Ok(())
+      }
+      Var::Default(ref var, ref default) => {
+        self.visit_var(var)?;
+        self.visit(default)
+      }
+    }
+  }
+
+  fn walk_body(&mut self, body: &[Node]) -> Result<(), Self::Error> {
+    for node in body {
+      self.visit(node)?;
+    }
+    Ok(())
+  }
+}
+
+// Describe the first difference between two trees, or None if they're equal
+// eg `at Block[2].If.cond: expected Int(1), found Int(2)`
+pub fn diff(a: &Node, b: &Node) -> Option<String> {
+  diff_node(a, b, "")
+}
+
+fn variant_name(node: &Node) -> &'static str {
+  match *node {
+    Node::Block(_) => "Block",
+    Node::Stmt(_) => "Stmt",
+    Node::Catch { .. } => "Catch",
+    Node::Assn { .. } => "Assn",
+    Node::If { .. } => "If",
+    Node::ElseIf { .. } => "ElseIf",
+    Node::Else { .. } => "Else",
+    Node::For { .. } => "For",
+    Node::While { .. } => "While",
+    Node::Loop { .. } => "Loop",
+    Node::Return(_) => "Return",
+    Node::Tuple(_) => "Tuple",
+    Node::Discard => "Discard",
+    Node::Break(_) => "Break",
+    Node::Continue(_) => "Continue",
+    Node::Expr => "Expr",
+    Node::Pass => "Pass",
+    Node::Index { .. } => "Index",
+    Node::Method { .. } => "Method",
+    Node::Func { .. } => "Func",
+    Node::Lambda { .. } => "Lambda",
+    Node::Call { .. } => "Call",
+    Node::BinExpr { .. } => "BinExpr",
+    Node::UnExpr { .. } => "UnExpr",
+    Node::Null => "Null",
+    Node::Bool(_) => "Bool",
+    Node::Float(_) => "Float",
+    Node::Int(_) => "Int",
+    Node::Str(_) => "Str",
+    Node::Name(_) => "Name",
+    Node::Table => "Table",
+  }
+}
+
+#[allow(clippy::needless_borrowed_reference)]
+fn diff_node(a: &Node, b: &Node, path: &str) -> Option<String> {
+  if a == b {
+    return None;
+  }
+
+  let here = if path.is_empty() {
+    String::from(variant_name(a))
+  } else {
+    format!("{}.{}", path, variant_name(a))
+  };
+  let field = |f: &str| format!("{}.{}", here, f);
+
+  let found = match (a, b) {
+    (&Node::Block(ref x), &Node::Block(ref y)) | (&Node::Tuple(ref x), &Node::Tuple(ref y)) => {
+      diff_body(x, y, &here)
+    }
+
+    (&Node::Stmt(ref x), &Node::Stmt(ref y))
+    | (&Node::Return(Some(ref x)), &Node::Return(Some(ref y))) => diff_node(x, y, &here),
+
+    (
+      &Node::Catch {
+        body: ref x,
+        finally: ref xf,
+      },
+      &Node::Catch {
+        body: ref y,
+        finally: ref yf,
+      },
+    ) => diff_body(x, y, &field("body")).or_else(|| match (xf, yf) {
+      (&Some(ref x), &Some(ref y)) => diff_body(x, y, &field("finally")),
+      _ => None,
+    }),
+
+    (
+      &Node::Assn {
+        lhs: ref xl,
+        rhs: ref xr,
+      },
+      &Node::Assn {
+        lhs: ref yl,
+        rhs: ref yr,
+      },
+    ) => {
+      if xl != yl {
+        Some(describe(&field("lhs"), xl, yl))
+      } else {
+        diff_node(xr, yr, &field("rhs"))
+      }
+    }
+
+    (
+      &Node::If {
+        cond: ref xc,
+        body: ref xb,
+        els: ref xe,
+      },
+      &Node::If {
+        cond: ref yc,
+        body: ref yb,
+        els: ref ye,
+      },
+    ) => diff_node(xc, yc, &field("cond"))
+      .or_else(|| diff_body(xb, yb, &field("body")))
+      .or_else(|| match (xe, ye) {
+        (&Some(ref x), &Some(ref y)) => diff_node(x, y, &field("els")),
+        _ => None,
+      }),
+
+    (
+      &Node::ElseIf {
+        cond: ref xc,
+        body: ref xb,
+      },
+      &Node::ElseIf {
+        cond: ref yc,
+        body: ref yb,
+      },
+    ) => diff_node(xc, yc, &field("cond")).or_else(|| diff_body(xb, yb, &field("body"))),
+
+    (&Node::Else { body: ref x }, &Node::Else { body: ref y })
+    | (&Node::Loop { body: ref x }, &Node::Loop { body: ref y })
+    | (&Node::Func { body: ref x, .. }, &Node::Func { body: ref y, .. }) => {
+      diff_body(x, y, &field("body"))
+    }
+
+    (
+      &Node::For {
+        decl: ref xd,
+        expr: ref xe,
+        body: ref xb,
+      },
+      &Node::For {
+        decl: ref yd,
+        expr: ref ye,
+        body: ref yb,
+      },
+    ) => {
+      if xd != yd {
+        Some(describe(&field("decl"), xd, yd))
+      } else {
+        diff_node(xe, ye, &field("expr")).or_else(|| diff_body(xb, yb, &field("body")))
+      }
+    }
+
+    (
+      &Node::While {
+        expr: ref xe,
+        body: ref xb,
+      },
+      &Node::While {
+        expr: ref ye,
+        body: ref yb,
+      },
+    ) => diff_node(xe, ye, &field("expr")).or_else(|| diff_body(xb, yb, &field("body"))),
+
+    (
+      &Node::Index {
+        lhs: ref xl,
+        rhs: ref xr,
+      },
+      &Node::Index {
+        lhs: ref yl,
+        rhs: ref yr,
+      },
+    )
+    | (
+      &Node::BinExpr {
+        lhs: ref xl,
+        rhs: ref xr,
+        ..
+      },
+      &Node::BinExpr {
+        lhs: ref yl,
+        rhs: ref yr,
+        ..
+      },
+    ) => diff_node(xl, yl, &field("lhs")).or_else(|| diff_node(xr, yr, &field("rhs"))),
+
+    (
+      &Node::Method {
+        owner: ref xo,
+        method: ref xm,
+        args: ref xa,
+      },
+      &Node::Method {
+        owner: ref yo,
+        method: ref ym,
+        args: ref ya,
+      },
+    ) => diff_node(xo, yo, &field("owner"))
+      .or_else(|| diff_node(xm, ym, &field("method")))
+      .or_else(|| diff_body(xa, ya, &field("args"))),
+
+    (&Node::Lambda { expr: ref x, .. }, &Node::Lambda { expr: ref y, .. }) => {
+      diff_node(x, y, &field("expr"))
+    }
+
+    (
+      &Node::Call {
+        func: ref xf,
+        args: ref xa,
+      },
+      &Node::Call {
+        func: ref yf,
+        args: ref ya,
+      },
+    ) => diff_node(xf, yf, &field("func")).or_else(|| diff_body(xa, ya, &field("args"))),
+
+    (&Node::UnExpr { val: ref x, .. }, &Node::UnExpr { val: ref y, .. }) => {
+      diff_node(x, y, &field("val"))
+    }
+
+    _ => None,
+  };
+
+  // if none of the children differ, it's something about this node itself
+  Some(found.unwrap_or_else(|| describe(path, a, b)))
+}
+
+fn diff_body(a: &[Node], b: &[Node], path: &str) -> Option<String> {
+  for (i, x) in a.iter().enumerate() {
+    if let Some(y) = b.get(i) {
+      if let Some(found) = diff_node(x, y, &format!("{}[{}]", path, i)) {
+        return Some(found);
+      }
+    }
+  }
+
+  if a.len() != b.len() {
+    return Some(format!(
+      "at {}: expected {} nodes, found {}",
+      path,
+      a.len(),
+      b.len()
+    ));
+  }
+
+  None
+}
+
+fn describe<T: Debug>(path: &str, a: &T, b: &T) -> String {
+  if path.is_empty() {
+    format!("expected {:?}, found {:?}", a, b)
+  } else {
+    format!("at {}: expected {:?}, found {:?}", path, a, b)
+  }
 }
 
 // Return true if the next token in `it` is `kind`
@@ -141,7 +569,7 @@ fn use_token(it: &mut ParseIter, kind: Token) -> bool {
   }
 }
 
-// Panic if the next token in `it` is *not* `kind`
+// Consume the next token in `it` if it's `kind`, otherwise fail saying so
 fn require_token(it: &mut ParseIter, kind: Token) -> Result<(), ParseErrorKind> {
   if let Some(&tok) = it.peek() {
     if tok.node == kind {
@@ -149,14 +577,22 @@ fn require_token(it: &mut ParseIter, kind: Token) -> Result<(), ParseErrorKind>
       return Ok(());
     }
 
-    return Err(UnexpectedToken(tok.node.clone()));
+    return Err(Expected {
+      expected: vec![kind],
+      found: tok.node.clone(),
+    });
   }
 
-  return Err(UnexpectedEOF);
+  Err(UnexpectedEOF)
 }
 
 fn op_precedence(op: &Token) -> Op {
   match *op {
+    Token::LogOr => Op::Left(1),
+    Token::LogAnd => Op::Left(2),
+    Token::Or => Op::Left(4),
+    Token::And => Op::Left(6),
+    Token::Shl | Token::Shr => Op::Left(8),
     Token::Add | Token::Sub => Op::Left(10),
     Token::Div | Token::Mul => Op::Left(20),
     Token::Car => Op::Right(30),
@@ -173,15 +609,13 @@ fn parse_ml_expr(it: &mut ParseIter) -> Parse {
         let params = parse_fn_params(it)?;
         require_token(it, Token::Par)?;
         let body = parse_block(it)?;
-        Ok(Node::Func {
-          params: params,
-          body: body,
-        })
+        Ok(Node::Func { params, body })
       }
       Token::Catch => {
         it.next();
-        let block = parse_block(it)?;
-        Ok(Node::Catch(block))
+        let body = parse_block(it)?;
+        let finally = parse_finally(it)?;
+        Ok(Node::Catch { body, finally })
       }
       _ => parse_il_expr(it),
     };
@@ -190,6 +624,18 @@ fn parse_ml_expr(it: &mut ParseIter) -> Parse {
   Err(UnexpectedEOF)
 }
 
+// Parse the `finally` block that may follow a `catch` block on the next line
+fn parse_finally(it: &mut ParseIter) -> Result<Option<Vec<Node>>, ParseErrorKind> {
+  let mut ahead = it.clone();
+  if !use_token(&mut ahead, Token::End) || !use_token(&mut ahead, Token::Finally) {
+    return Ok(None);
+  }
+
+  *it = ahead;
+  let body = parse_block(it)?;
+  Ok(Some(body))
+}
+
 fn parse_il_expr(it: &mut ParseIter) -> Parse {
   if let Some(&tok) = it.peek() {
     return match tok.node {
@@ -199,7 +645,7 @@ fn parse_il_expr(it: &mut ParseIter) -> Parse {
         require_token(it, Token::Or)?;
         let expr = parse_il_expr(it)?;
         Ok(Node::Lambda {
-          params: params,
+          params,
           expr: Box::new(expr),
         })
       }
@@ -210,82 +656,67 @@ fn parse_il_expr(it: &mut ParseIter) -> Parse {
   Err(UnexpectedEOF)
 }
 
+// `x |> f(y)` binds looser than every other operator and becomes `f(x, y)`
+// likewise, `x |> obj:m(y)` becomes `obj:m(x, y)`
 fn parse_bin_expr(it: &mut ParseIter) -> Parse {
-  let mut expr = parse_un_expr(it)?;
+  let mut expr = parse_op_expr(it)?;
+
+  while use_token(it, Token::Pipe) {
+    expr = match parse_op_expr(it)? {
+      Node::Call { func, mut args } => {
+        args.insert(0, expr);
+        Node::Call { func, args }
+      }
+      Node::Method {
+        owner,
+        method,
+        mut args,
+      } => {
+        args.insert(0, expr);
+        Node::Method {
+          owner,
+          method,
+          args,
+        }
+      }
+      func => Node::Call {
+        func: Box::new(func),
+        args: vec![expr],
+      },
+    };
+  }
+
+  Ok(expr)
+}
+
+fn parse_op_expr(it: &mut ParseIter) -> Parse {
+  parse_climb(it, 0)
+}
 
-  // prevents this from breaking the LHS until we know we made it
-  // otherwise, things like (2 + 3) * 4 get restructured into 2 + (3 * 4)
-  let mut break_left = false;
+// Precedence climbing: parse operators binding at least as tightly as `min`
+fn parse_climb(it: &mut ParseIter, min: u32) -> Parse {
+  let mut expr = parse_un_expr(it)?;
 
   while let Some(&tok) = it.peek() {
-    let prec = op_precedence(&tok.node);
+    // a right-associative operator lets its RHS contain the same operator
+    let (prec, next_min) = match op_precedence(&tok.node) {
+      Op::Left(n) => (n, n + 1),
+      Op::Right(n) => (n, n),
+      Op::None => break,
+    };
 
-    if let Op::None = prec {
+    if prec < min {
       break;
     }
 
     it.next();
 
-    let rhs = parse_un_expr(it)?;
-
-    expr = match (break_left, expr.clone()) {
-      (
-        true,
-        Node::BinExpr {
-          lhs: cur_lhs,
-          op: cur_op,
-          rhs: cur_rhs,
-        },
-      ) => {
-        let cur_prec = op_precedence(&cur_op);
-        match (cur_prec, prec) {
-          // these should never happen
-          (_, Op::None) => break,
-          (Op::None, _) => break,
-
-          // left-to-right
-          // there has to be a better way to handle this, no?
-          (Op::Left(n), Op::Left(m)) if n >= m => Node::BinExpr {
-            lhs: Box::new(expr),
-            op: tok.node.clone(),
-            rhs: Box::new(rhs),
-          },
-          (Op::Right(n), Op::Right(m)) if n > m => Node::BinExpr {
-            lhs: Box::new(expr),
-            op: tok.node.clone(),
-            rhs: Box::new(rhs),
-          },
-          (Op::Right(n), Op::Left(m)) if n >= m => Node::BinExpr {
-            lhs: Box::new(expr),
-            op: tok.node.clone(),
-            rhs: Box::new(rhs),
-          },
-          (Op::Left(n), Op::Right(m)) if n >= m => Node::BinExpr {
-            lhs: Box::new(expr),
-            op: tok.node.clone(),
-            rhs: Box::new(rhs),
-          },
-
-          // right-to-left
-          _ => Node::BinExpr {
-            lhs: cur_lhs,
-            op: cur_op,
-            rhs: Box::new(Node::BinExpr {
-              lhs: cur_rhs,
-              op: tok.node.clone(),
-              rhs: Box::new(rhs),
-            }),
-          },
-        }
-      }
-      _ => Node::BinExpr {
-        lhs: Box::new(expr),
-        op: tok.node.clone(),
-        rhs: Box::new(rhs),
-      },
+    let rhs = parse_climb(it, next_min)?;
+    expr = Node::BinExpr {
+      lhs: Box::new(expr),
+      op: tok.node.clone(),
+      rhs: Box::new(rhs),
     };
-
-    break_left = true;
   }
 
   Ok(expr)
@@ -302,6 +733,22 @@ fn parse_un_expr(it: &mut ParseIter) -> Parse {
           val: Box::new(val),
         })
       }
+
+      // `not` is just a wordier `!`
+      Token::LogNot => {
+        it.next();
+
+        // if there's nothing to negate, `not` was probably meant as a name
+        let left = it.len();
+        let val = match parse_un_expr(it) {
+          Err(_) if it.len() == left => return Err(ReservedWord(Token::LogNot)),
+          res => res?,
+        };
+        Ok(Node::UnExpr {
+          op: Token::Not,
+          val: Box::new(val),
+        })
+      }
       _ => parse_simple(it),
     };
   }
@@ -344,6 +791,14 @@ fn parse_fn_params(it: &mut ParseIter) -> Result<Vec<String>, ParseErrorKind> {
           break;
         }
       }
+      // unused parameters are still named `_`
+      Token::Und => {
+        it.next();
+        params.push(String::from("_"));
+        if !use_token(it, Token::Com) {
+          break;
+        }
+      }
       _ => break,
     }
   }
@@ -376,7 +831,7 @@ fn parse_simple(it: &mut ParseIter) -> Parse {
         atom = Node::Method {
           owner: Box::new(atom),
           method: Box::new(method),
-          args: args,
+          args,
         };
       }
 
@@ -384,7 +839,7 @@ fn parse_simple(it: &mut ParseIter) -> Parse {
         let args = parse_fn_args(it)?;
         atom = Node::Call {
           func: Box::new(atom),
-          args: args,
+          args,
         };
       }
 
@@ -444,20 +899,6 @@ fn parse_name_as_str(it: &mut ParseIter) -> Parse {
   Err(UnexpectedEOF)
 }
 
-fn parse_name(it: &mut ParseIter) -> Parse {
-  if let Some(&tok) = it.peek() {
-    return match tok.node {
-      Token::Name(ref x) => {
-        it.next();
-        Ok(Node::Name(x.clone()))
-      }
-      ref x => Err(UnexpectedToken(x.clone())),
-    };
-  }
-
-  Err(UnexpectedEOF)
-}
-
 fn parse_quark(it: &mut ParseIter) -> Parse {
   if let Some(&tok) = it.peek() {
     return match tok.node {
@@ -489,6 +930,7 @@ fn parse_quark(it: &mut ParseIter) -> Parse {
         it.next();
         Ok(Node::Table)
       }
+      ref x if x.category() == TokenCategory::Keyword => Err(ReservedWord(x.clone())),
       ref x => Err(UnexpectedToken(x.clone())),
     };
   }
@@ -502,8 +944,19 @@ fn parse_decl(it: &mut ParseIter) -> Result<Var, ParseErrorKind> {
       Token::Sql => {
         it.next();
         let mut pieces: Vec<Var> = Vec::new();
+        let mut has_default = false;
         loop {
-          let new_piece = parse_decl(it)?;
+          let mut new_piece = parse_decl(it)?;
+
+          // once one piece has a default, every piece after it needs one too
+          if use_token(it, Token::Ass) {
+            let default = parse_il_expr(it)?;
+            new_piece = Var::Default(Box::new(new_piece), Box::new(default));
+            has_default = true;
+          } else if has_default {
+            return Err(NonTrailingDefault);
+          }
+
           pieces.push(new_piece);
           if !use_token(it, Token::Com) {
             break;
@@ -516,6 +969,10 @@ fn parse_decl(it: &mut ParseIter) -> Result<Var, ParseErrorKind> {
         it.next();
         Ok(Var::Single(x.clone()))
       }
+      Token::Und => {
+        it.next();
+        Ok(Var::Discard)
+      }
       ref x => Err(UnexpectedToken(x.clone())),
     };
   }
@@ -529,8 +986,19 @@ fn parse_place(it: &mut ParseIter) -> Result<Place, ParseErrorKind> {
       Token::Sql => {
         it.next();
         let mut pieces: Vec<Place> = Vec::new();
+        let mut has_default = false;
         loop {
-          let new_piece = parse_place(it)?;
+          let mut new_piece = parse_place(it)?;
+
+          // same as `parse_decl`, only trailing pieces can have defaults
+          if use_token(it, Token::Ass) {
+            let default = parse_il_expr(it)?;
+            new_piece = Place::Default(Box::new(new_piece), Box::new(default));
+            has_default = true;
+          } else if has_default {
+            return Err(NonTrailingDefault);
+          }
+
           pieces.push(new_piece);
           if !use_token(it, Token::Com) {
             break;
@@ -540,6 +1008,11 @@ fn parse_place(it: &mut ParseIter) -> Result<Place, ParseErrorKind> {
         Ok(Place::Multi(pieces))
       }
 
+      Token::Und => {
+        it.next();
+        Ok(Place::Single(Box::new(Node::Discard)))
+      }
+
       _ => {
         let node = parse_il_expr(it)?;
         Ok(Place::Single(Box::new(node)))
@@ -565,8 +1038,9 @@ fn parse_assn(it: &mut ParseIter) -> Parse {
       }
 
       _ => match place {
+        Place::Single(ref bx) if **bx == Node::Discard => Err(UnusedPlaces),
         Place::Single(bx) => Ok(Node::Stmt(bx)),
-        Place::Multi(_) => Err(UnusedPlaces),
+        Place::Multi(_) | Place::Default(..) => Err(UnusedPlaces),
       },
     };
   }
@@ -574,17 +1048,82 @@ fn parse_assn(it: &mut ParseIter) -> Parse {
   Err(UnexpectedEOF)
 }
 
+// Wrap `node` in a logical not, for `unless` and `until`
+fn negate(node: Node) -> Node {
+  Node::UnExpr {
+    op: Token::Not,
+    val: Box::new(node),
+  }
+}
+
+// Parse the condition and body of an `else if` / `elif`
+fn parse_else_if(it: &mut ParseIter) -> Parse {
+  let cond = parse_bin_expr(it)?;
+  let body = parse_block(it)?;
+  Ok(Node::ElseIf {
+    cond: Box::new(cond),
+    body,
+  })
+}
+
+// Parse the value(s) of a `return`, packing more than one into a tuple
+fn parse_return_vals(it: &mut ParseIter) -> Parse {
+  let val = parse_ml_expr(it)?;
+
+  if !peek_token(it, Token::Com) {
+    return Ok(val);
+  }
+
+  let mut vals = vec![val];
+  while use_token(it, Token::Com) {
+    vals.push(parse_il_expr(it)?);
+  }
+
+  Ok(Node::Tuple(vals))
+}
+
+// Wrap `stmt` in an `If` when it's followed by a trailing `if COND`
+fn parse_guard(it: &mut ParseIter, stmt: Node) -> Parse {
+  if !use_token(it, Token::If) {
+    return Ok(stmt);
+  }
+
+  let cond = parse_bin_expr(it)?;
+  Ok(Node::If {
+    cond: Box::new(cond),
+    body: vec![stmt],
+    els: None,
+  })
+}
+
+// Parse how many loops a `break` or `continue` applies to, defaulting to one
+fn parse_level(it: &mut ParseIter) -> Result<usize, ParseErrorKind> {
+  if let Some(&tok) = it.peek() {
+    if let Token::Int(x) = tok.node {
+      if x < 1 {
+        return Err(UnexpectedToken(tok.node.clone()));
+      }
+      it.next();
+      return Ok(x as usize);
+    }
+  }
+
+  Ok(1)
+}
+
 fn parse_stmt(it: &mut ParseIter) -> Parse {
   if let Some(&tok) = it.peek() {
     return match tok.node {
       Token::Break => {
         it.next();
-        Ok(Node::Break)
+        let level = parse_level(it)?;
+        parse_guard(it, Node::Break(level))
       }
 
       Token::Continue => {
         it.next();
-        Ok(Node::Continue)
+        let level = parse_level(it)?;
+        parse_guard(it, Node::Continue(level))
       }
 
       Token::If => {
@@ -593,7 +1132,19 @@ fn parse_stmt(it: &mut ParseIter) -> Parse {
         let body = parse_block(it)?;
         Ok(Node::If {
           cond: Box::new(cond),
-          body: body,
+          body,
+          els: None,
+        })
+      }
+
+      // `unless c` is `if !c`
+      Token::Unless => {
+        it.next();
+        let cond = parse_bin_expr(it)?;
+        let body = parse_block(it)?;
+        Ok(Node::If {
+          cond: Box::new(negate(cond)),
+          body,
           els: None,
         })
       }
@@ -601,18 +1152,18 @@ fn parse_stmt(it: &mut ParseIter) -> Parse {
       Token::Else => {
         it.next();
         if use_token(it, Token::If) {
-          let cond = parse_bin_expr(it)?;
-          let body = parse_block(it)?;
-          Ok(Node::ElseIf {
-            cond: Box::new(cond),
-            body: body,
-          })
+          parse_else_if(it)
         } else {
           let body = parse_block(it)?;
-          Ok(Node::Else { body: body })
+          Ok(Node::Else { body })
         }
       }
 
+      Token::Elif => {
+        it.next();
+        parse_else_if(it)
+      }
+
       Token::For => {
         it.next();
         let decl = parse_decl(it)?;
@@ -620,9 +1171,9 @@ fn parse_stmt(it: &mut ParseIter) -> Parse {
         let expr = parse_il_expr(it)?;
         let body = parse_block(it)?;
         Ok(Node::For {
-          decl: decl,
+          decl,
           expr: Box::new(expr),
-          body: body,
+          body,
         })
       }
 
@@ -632,25 +1183,36 @@ fn parse_stmt(it: &mut ParseIter) -> Parse {
         let body = parse_block(it)?;
         Ok(Node::While {
           expr: Box::new(expr),
-          body: body,
+          body,
+        })
+      }
+
+      // `until c` is `while !c`
+      Token::Until => {
+        it.next();
+        let expr = parse_il_expr(it)?;
+        let body = parse_block(it)?;
+        Ok(Node::While {
+          expr: Box::new(negate(expr)),
+          body,
         })
       }
 
       Token::Loop => {
         it.next();
         let body = parse_block(it)?;
-        Ok(Node::Loop { body: body })
+        Ok(Node::Loop { body })
       }
 
       Token::Return => {
         it.next();
-        let val = if peek_token(it, Token::End) {
+        let val = if peek_token(it, Token::End) || peek_token(it, Token::If) {
           None
         } else {
-          let val = parse_ml_expr(it)?;
+          let val = parse_return_vals(it)?;
           Some(Box::new(val))
         };
-        Ok(Node::Return(val))
+        parse_guard(it, Node::Return(val))
       }
 
       Token::Pass => {
@@ -660,7 +1222,10 @@ fn parse_stmt(it: &mut ParseIter) -> Parse {
 
       Token::Func | Token::Catch => parse_ml_expr(it).map(|expr| Node::Stmt(Box::new(expr))),
 
-      _ => parse_assn(it),
+      _ => {
+        let stmt = parse_assn(it)?;
+        parse_guard(it, stmt)
+      }
     };
   }
 
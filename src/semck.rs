@@ -1,107 +1,256 @@
+use lexer::Token;
 use parser::Node;
 use parser::Place;
+use parser::Var;
+use parser::Visitor;
 
 type Check = Result<(), CheckErrorKind>;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum CheckErrorKind {
   NotInLoop,
+  LevelTooDeep(usize),
   MissingIf,
-  NotPlace,
+  NotPlace(Node),
+  ConstantCondition,
+  EmptyLoop,
+  Shadowed(String),
+  NoReturn,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct SemChecker {
-  in_loop: bool,
+  loop_depth: usize,
   has_if: bool,
+  warnings: Vec<CheckErrorKind>,
+  scopes: Vec<Vec<String>>,
+}
+
+impl Default for SemChecker {
+  fn default() -> SemChecker {
+    SemChecker::new()
+  }
 }
 
 impl SemChecker {
   pub fn new() -> SemChecker {
     SemChecker {
-      in_loop: false,
+      loop_depth: 0,
       has_if: false,
+      warnings: Vec::new(),
+      scopes: vec![Vec::new()],
     }
   }
 
-  pub fn check(&mut self, node: &mut Node) -> Check {
-    println!("checking: {:?}", node);
-    match *node {
-      Node::Stmt(ref mut bx) => {
-        self.check(bx)?;
+  // Non-fatal problems found during `check`
+  pub fn warnings(&self) -> &[CheckErrorKind] {
+    &self.warnings
+  }
+
+  pub fn check(&mut self, node: &Node) -> Check {
+    self.visit(node)
+  }
+
+  fn check_loop(&mut self, node: &Node) -> Check {
+    self.loop_depth += 1;
+    let res = self.walk_node(node);
+    self.loop_depth -= 1;
+    res
+  }
+
+  // Check `node` in a new scope introducing `names`, which are loop variables
+  // or parameters and so probably shouldn't shadow anything
+  fn check_scope(&mut self, names: Vec<String>, node: &Node) -> Check {
+    for name in &names {
+      if self.is_bound(name) {
+        self.warnings.push(CheckErrorKind::Shadowed(name.clone()));
       }
+    }
+
+    self.scopes.push(names);
+    let res = match *node {
+      Node::For { .. } => self.check_loop(node),
+      _ => self.walk_node(node),
+    };
+    self.scopes.pop();
+    res
+  }
+
+  // Check a function body, which can't break out of loops around the function
+  fn check_func(&mut self, params: &[String], node: &Node) -> Check {
+    // `_` parameters are discarded, so they can't shadow anything
+    let names = params.iter().filter(|p| *p != "_").cloned().collect();
 
-      Node::Block(ref mut ls) | Node::Catch(ref mut ls) => for mut n in ls {
-        self.check(&mut n)?;
-      },
+    let loop_depth = self.loop_depth;
+    self.loop_depth = 0;
+    let res = self.check_scope(names, node);
+    self.loop_depth = loop_depth;
+    res
+  }
+
+  fn is_bound(&self, name: &str) -> bool {
+    self.scopes.iter().any(|scope| scope.iter().any(|n| n == name))
+  }
 
-      Node::Loop { ref mut body } => {
-        self.in_loop = true;
-        for mut n in body {
-          self.check(&mut n)?;
+  fn bind_place(&mut self, place: &Place) {
+    match *place {
+      Place::Single(ref node) => {
+        if let Node::Name(ref name) = **node {
+          if !self.is_bound(name) {
+            let scope = self.scopes.len() - 1;
+            self.scopes[scope].push(name.clone());
+          }
         }
-        self.in_loop = false;
       }
+      Place::Multi(ref places) => {
+        for pl in places {
+          self.bind_place(pl);
+        }
+      }
+      Place::Default(ref place, _) => self.bind_place(place),
+    }
+  }
 
-      Node::While {
-        ref mut body,
-        expr: _,
-      } => {
-        self.in_loop = true;
-        for mut n in body {
-          self.check(&mut n)?;
+  fn check_cond(&mut self, node: &Node) {
+    match *node {
+      // `while true` is an intentional infinite loop
+      Node::Bool(true) => {}
+      // and so is `until false`
+      Node::UnExpr {
+        op: Token::Not,
+        ref val,
+      } if **val == Node::Bool(false) => {}
+      _ if is_const(node) => self.warnings.push(CheckErrorKind::ConstantCondition),
+      _ => {}
+    }
+  }
+
+  // Check that every leaf of `place`, however deeply nested, can be assigned
+  fn check_place(&self, place: &Place) -> Check {
+    match *place {
+      Place::Single(ref node) => self.is_place(node),
+      Place::Multi(ref places) => {
+        for pl in places {
+          self.check_place(pl)?;
         }
-        self.in_loop = false;
+        Ok(())
+      }
+      Place::Default(ref place, _) => self.check_place(place),
+    }
+  }
+
+  fn is_place(&self, node: &Node) -> Check {
+    match *node {
+      Node::Name(_) | Node::Index { .. } | Node::Discard => Ok(()),
+      _ => Err(CheckErrorKind::NotPlace(node.clone())),
+    }
+  }
+}
+
+impl Visitor for SemChecker {
+  type Error = CheckErrorKind;
+
+  fn visit(&mut self, node: &Node) -> Check {
+    match *node {
+      Node::Loop { .. } => self.check_loop(node),
+
+      Node::While { ref expr, .. } => {
+        self.check_cond(expr);
+        self.check_loop(node)
       }
 
       Node::For {
-        ref mut body,
-        decl: _,
-        expr: _,
+        ref decl, ref expr, ..
       } => {
-        self.in_loop = true;
-        for mut n in body {
-          self.check(&mut n)?;
+        if let Node::Table = **expr {
+          self.warnings.push(CheckErrorKind::EmptyLoop);
         }
-        self.in_loop = false;
+
+        let mut names = Vec::new();
+        var_names(decl, &mut names);
+        self.check_scope(names, node)
+      }
+
+      Node::Func {
+        ref params,
+        ref body,
+      } => {
+        // calling this yields null, which is probably not what was intended
+        if !has_return(body) {
+          self.warnings.push(CheckErrorKind::NoReturn);
+        }
+        self.check_func(params, node)
       }
 
-      Node::Break | Node::Continue => {
-        if !self.in_loop {
+      Node::Lambda { ref params, .. } => self.check_func(params, node),
+
+      Node::Break(level) | Node::Continue(level) => {
+        if self.loop_depth == 0 {
           return Err(CheckErrorKind::NotInLoop);
         }
+        if level > self.loop_depth {
+          return Err(CheckErrorKind::LevelTooDeep(level));
+        }
+        Ok(())
       }
 
-      Node::Assn { rhs: _, ref lhs } => {
+      Node::Assn { ref lhs, .. } => {
         self.check_place(lhs)?;
+        self.bind_place(lhs);
+        self.walk_node(node)
       }
 
       // TODO add if-elif-else checks
-      _ => {}
+      _ => self.walk_node(node),
     }
-
-    Ok(())
   }
+}
 
-  fn check_place(&self, place: &Place) -> Check {
-    match *place {
-      Place::Single(ref node) => {
-        self.is_place(node)?;
-      }
-      Place::Multi(ref places) => {
-        let mut valid = true;
-        for pl in places {
-          self.check_place(&pl)?;
-        }
+// Collect every name declared by `var`
+fn var_names(var: &Var, names: &mut Vec<String>) {
+  match *var {
+    Var::Single(ref name) => names.push(name.clone()),
+    Var::Multi(ref vars) => {
+      for var in vars {
+        var_names(var, names);
       }
-    };
-    Ok(())
+    }
+    Var::Default(ref var, _) => var_names(var, names),
+    Var::Discard => {}
   }
+}
 
-  fn is_place(&self, node: &Node) -> Check {
+// Finds a `return` with a value that belongs to the function being walked
+struct ReturnFinder;
+
+impl Visitor for ReturnFinder {
+  type Error = ();
+
+  fn visit(&mut self, node: &Node) -> Result<(), ()> {
     match *node {
-      Node::Name(_) | Node::Index { lhs: _, rhs: _ } => Ok(()),
-      _ => Err(CheckErrorKind::NotPlace),
+      Node::Return(Some(_)) => Err(()),
+      // nested functions return from themselves
+      Node::Func { .. } | Node::Lambda { .. } => Ok(()),
+      _ => self.walk_node(node),
     }
   }
 }
+
+// Return true if `body` returns a value from its enclosing function
+fn has_return(body: &[Node]) -> bool {
+  ReturnFinder.walk_body(body).is_err()
+}
+
+// Return true if `node` always evaluates to the same value
+fn is_const(node: &Node) -> bool {
+  match *node {
+    Node::Null | Node::Bool(_) | Node::Float(_) | Node::Int(_) | Node::Str(_) | Node::Table => true,
+    Node::UnExpr { ref val, .. } => is_const(val),
+    Node::BinExpr { ref lhs, ref rhs, .. } => is_const(lhs) && is_const(rhs),
+    _ => false,
+  }
+}
+
+#[cfg(test)]
+#[path = "./tests/semck.rs"]
+mod tests;
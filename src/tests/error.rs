@@ -0,0 +1,65 @@
+use super::*;
+use super::super::lexer;
+use super::super::lexer::Token;
+use super::super::parser::ParseErrorKind;
+
+#[test]
+fn test_render_error() {
+  let mut map = CodeMap::new();
+  let file = map.add_file(
+    String::from("_test"),
+    String::from("x = 1\ny = (2 +) * 3\n"),
+  );
+  let tokens = lexer::lex(&file).unwrap();
+
+  // the `)` that shouldn't be there
+  assert_eq!(tokens[9].node, Token::Par);
+  let err = Spanned {
+    node: ParseErrorKind::UnexpectedToken(Token::Par),
+    span: tokens[9].span,
+  };
+
+  assert_eq!(
+    render_error(&map, &err),
+    "error: UnexpectedToken(Par)
+ --> _test:2:9
+  |
+2 | y = (2 +) * 3
+  |         ^
+"
+  );
+}
+
+#[test]
+fn test_render_lex_error() {
+  let mut map = CodeMap::new();
+  let file = map.add_file(String::from("_test"), String::from("s = 'caf\\u{110000}'"));
+  let err = lexer::lex(&file).unwrap_err();
+
+  assert_eq!(
+    render_error(&map, &err),
+    "error: BadCodepoint
+ --> _test:1:9
+  |
+1 | s = 'caf\\u{110000}'
+  |         ^^^^^^^^^^
+"
+  );
+}
+
+#[test]
+fn test_render_multiline_span() {
+  let mut map = CodeMap::new();
+  let file = map.add_file(String::from("_test"), String::from("s = 'open\nstring"));
+  let err = lexer::lex(&file).unwrap_err();
+
+  assert_eq!(
+    render_error(&map, &err),
+    "error: UnclosedStr
+ --> _test:1:5
+  |
+1 | s = 'open
+  |     ^^^^^
+"
+  );
+}
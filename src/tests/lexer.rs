@@ -5,7 +5,7 @@ use codemap::CodeMap;
 fn get_tokens(source: &str) -> Vec<Spanned<Token>> {
   let mut map = CodeMap::new();
   let file = map.add_file(String::from("_test"), String::from(source));
-  lex(&file)
+  lex(&file).unwrap()
 }
 
 #[test]
@@ -26,15 +26,56 @@ fn lex_numbers() {
   assert_eq!(tokens[9].node, EOF);
 }
 
+#[test]
+fn lex_number_separators() {
+  let source = "1_000 1_000.000_5 1_2_3 0.5_5 9_223_372_036_854_775_807";
+  let tokens = get_tokens(source);
+
+  assert_eq!(tokens.len(), 7);
+  assert_eq!(tokens[0].node, Int(1000));
+  assert_eq!(tokens[1].node, Float(1000.0005));
+  assert_eq!(tokens[2].node, Int(123));
+  assert_eq!(tokens[3].node, Float(0.55));
+  assert_eq!(tokens[4].node, Int(9223372036854775807));
+  assert_eq!(tokens[5].node, End);
+  assert_eq!(tokens[6].node, EOF);
+
+  // a leading underscore makes a name, not a number
+  let tokens = get_tokens("_1");
+  assert_eq!(tokens[0].node, Name(String::from("_1")));
+}
+
+#[test]
+fn lex_bad_numbers() {
+  let cases = vec![
+    ("1_", "1_"),
+    ("1__0", "1__0"),
+    ("1_.5", "1_.5"),
+    ("1._5", "1._5"),
+    ("1.5_", "1.5_"),
+    ("1.2.3", "1.2.3"),
+    ("x = 99999999999999999999", "99999999999999999999"),
+  ];
+
+  for (source, slice) in cases {
+    let mut map = CodeMap::new();
+    let file = map.add_file(String::from("_test"), String::from(source));
+
+    let err = lex(&file).unwrap_err();
+    assert_eq!(err.node, LexErrorKind::BadNumber);
+    assert_eq!(file.source_slice(err.span), slice);
+  }
+}
+
 #[test]
 fn lex_string() {
   let source = "'hello' 'this\\nis\\nmultiline' 'this\\\\is\\\\escaped' 'this\\tis\\ttabbed' 'this
 is
 real
-multiline' 'and this is unclosed";
+multiline'";
   let tokens = get_tokens(source);
 
-  assert_eq!(tokens.len(), 8);
+  assert_eq!(tokens.len(), 7);
   assert_eq!(tokens[0].node, Str(String::from("hello")));
   assert_eq!(tokens[1].node, Str(String::from("this\nis\nmultiline")));
   assert_eq!(tokens[2].node, Str(String::from("this\\is\\escaped")));
@@ -43,19 +84,95 @@ multiline' 'and this is unclosed";
     tokens[4].node,
     Str(String::from("this\nis\nreal\nmultiline"))
   );
-  assert_eq!(
-    tokens[5].node,
-    UnclosedStr(String::from("and this is unclosed"))
+  assert_eq!(tokens[5].node, End);
+  assert_eq!(tokens[6].node, EOF);
+}
+
+#[test]
+fn lex_string_escapes() {
+  let source = "'\\x41\\x7e' '\\u{1F600}' '\\u{e9}t\\u{E9}' 'caf\\u{e9} \\x41'";
+  let tokens = get_tokens(source);
+
+  assert_eq!(tokens.len(), 6);
+  assert_eq!(tokens[0].node, Str(String::from("A~")));
+  assert_eq!(tokens[1].node, Str(String::from("\u{1F600}")));
+  assert_eq!(tokens[2].node, Str(String::from("été")));
+  assert_eq!(tokens[3].node, Str(String::from("café A")));
+  assert_eq!(tokens[4].node, End);
+  assert_eq!(tokens[5].node, EOF);
+}
+
+#[test]
+fn lex_bad_escapes() {
+  let cases = vec![
+    ("'\\u{110000}'", LexErrorKind::BadCodepoint, "\\u{110000}"),
+    ("'\\u{D800}'", LexErrorKind::BadCodepoint, "\\u{D800}"),
+    ("'ok' '\\x1'", LexErrorKind::BadEscape, "\\x1"),
+    ("'é\\xZZ'", LexErrorKind::BadEscape, "\\x"),
+    ("'\\xFF'", LexErrorKind::BadEscape, "\\xFF"),
+    ("'\\x80'", LexErrorKind::BadEscape, "\\x80"),
+    ("'\\u41'", LexErrorKind::BadEscape, "\\u"),
+    ("'\\u{}'", LexErrorKind::BadEscape, "\\u{}"),
+    ("'\\u{1234567}'", LexErrorKind::BadEscape, "\\u{123456"),
+  ];
+
+  for (source, kind, slice) in cases {
+    let mut map = CodeMap::new();
+    let file = map.add_file(String::from("_test"), String::from(source));
+
+    let err = lex(&file).unwrap_err();
+    assert_eq!(err.node, kind);
+    assert_eq!(file.source_slice(err.span), slice);
+  }
+}
+
+#[test]
+fn lex_raw_string() {
+  let source = "r'\\n' r'C:\\temp\\no\\escapes' r#'it's'# r##'a '# b'## r'' r rx";
+  let tokens = get_tokens(source);
+
+  assert_eq!(tokens.len(), 9);
+  assert_eq!(tokens[0].node, Str(String::from("\\n")));
+  assert_eq!(tokens[1].node, Str(String::from("C:\\temp\\no\\escapes")));
+  assert_eq!(tokens[2].node, Str(String::from("it's")));
+  assert_eq!(tokens[3].node, Str(String::from("a '# b")));
+  assert_eq!(tokens[4].node, Str(String::from("")));
+  assert_eq!(tokens[5].node, Name(String::from("r")));
+  assert_eq!(tokens[6].node, Name(String::from("rx")));
+  assert_eq!(tokens[7].node, End);
+  assert_eq!(tokens[8].node, EOF);
+
+  if let Str(ref contents) = tokens[0].node {
+    assert_eq!(contents.len(), 2);
+  }
+
+  for source in &["r'unclosed", "r#'unclosed'", "r##'unclosed'#"] {
+    let mut map = CodeMap::new();
+    let file = map.add_file(String::from("_test"), String::from(*source));
+    let err = lex(&file).unwrap_err();
+    assert_eq!(err.node, LexErrorKind::UnclosedStr);
+    assert_eq!(file.source_slice(err.span), *source);
+  }
+}
+
+#[test]
+fn lex_unclosed_string() {
+  let mut map = CodeMap::new();
+  let file = map.add_file(
+    String::from("_test"),
+    String::from("'hello' 'and this is unclosed"),
   );
-  assert_eq!(tokens[6].node, End);
-  assert_eq!(tokens[7].node, EOF);
+
+  let err = lex(&file).unwrap_err();
+  assert_eq!(err.node, LexErrorKind::UnclosedStr);
+  assert_eq!(file.source_slice(err.span), "'and this is unclosed");
 }
 
 #[test]
 fn lex_keywords() {
-  let source = "break catch continue else for fn if import in loop pass return save var while name true false null";
+  let source = "break catch continue else for fn if import in loop pass return save var while name true false null elif finally unless until";
   let tokens = get_tokens(source);
-  assert_eq!(tokens.len(), 21);
+  assert_eq!(tokens.len(), 25);
   assert_eq!(tokens[0].node, Break);
   assert_eq!(tokens[1].node, Catch);
   assert_eq!(tokens[2].node, Continue);
@@ -75,8 +192,43 @@ fn lex_keywords() {
   assert_eq!(tokens[16].node, Bool(true));
   assert_eq!(tokens[17].node, Bool(false));
   assert_eq!(tokens[18].node, Null);
-  assert_eq!(tokens[19].node, End);
-  assert_eq!(tokens[20].node, EOF);
+  assert_eq!(tokens[19].node, Elif);
+  assert_eq!(tokens[20].node, Finally);
+  assert_eq!(tokens[21].node, Unless);
+  assert_eq!(tokens[22].node, Until);
+  assert_eq!(tokens[23].node, End);
+  assert_eq!(tokens[24].node, EOF);
+}
+
+#[test]
+fn lex_underscore() {
+  let source = "_ _x x_ __";
+  let tokens = get_tokens(source);
+
+  assert_eq!(tokens.len(), 6);
+  assert_eq!(tokens[0].node, Und);
+  assert_eq!(tokens[1].node, Name(String::from("_x")));
+  assert_eq!(tokens[2].node, Name(String::from("x_")));
+  assert_eq!(tokens[3].node, Name(String::from("__")));
+  assert_eq!(tokens[4].node, End);
+  assert_eq!(tokens[5].node, EOF);
+}
+
+#[test]
+fn lex_word_operators() {
+  let mut map = CodeMap::new();
+  let file = map.add_file(String::from("_test"), String::from("and or not andy"));
+  let tokens = lex_with_categories(&file).unwrap();
+
+  assert_eq!(tokens.len(), 6);
+  assert_eq!(tokens[0].0.node, LogAnd);
+  assert_eq!(tokens[1].0.node, LogOr);
+  assert_eq!(tokens[2].0.node, LogNot);
+  assert_eq!(tokens[3].0.node, Name(String::from("andy")));
+  assert_eq!(tokens[0].1, TokenCategory::Keyword);
+  assert_eq!(tokens[1].1, TokenCategory::Keyword);
+  assert_eq!(tokens[2].1, TokenCategory::Keyword);
+  assert_eq!(tokens[3].1, TokenCategory::Identifier);
 }
 
 #[test]
@@ -127,6 +279,39 @@ fn lex_symbols() {
   assert_eq!(tokens[38].node, EOF);
 }
 
+#[test]
+fn lex_pipe() {
+  let source = "|> | > || |>>";
+  let tokens = get_tokens(source);
+  assert_eq!(tokens.len(), 9);
+  assert_eq!(tokens[0].node, Pipe);
+  assert_eq!(tokens[1].node, Or);
+  assert_eq!(tokens[2].node, Gt);
+  assert_eq!(tokens[3].node, Or);
+  assert_eq!(tokens[4].node, Or);
+  assert_eq!(tokens[5].node, Pipe);
+  assert_eq!(tokens[6].node, Gt);
+  assert_eq!(tokens[0].node.category(), TokenCategory::Operator);
+}
+
+#[test]
+fn lex_shifts() {
+  let source = "<< >> < < <<= >>> <=";
+  let tokens = get_tokens(source);
+  assert_eq!(tokens.len(), 11);
+  assert_eq!(tokens[0].node, Shl);
+  assert_eq!(tokens[1].node, Shr);
+  assert_eq!(tokens[2].node, Lt);
+  assert_eq!(tokens[3].node, Lt);
+  assert_eq!(tokens[4].node, Shl);
+  assert_eq!(tokens[5].node, Ass);
+  assert_eq!(tokens[6].node, Shr);
+  assert_eq!(tokens[7].node, Gt);
+  assert_eq!(tokens[8].node, Le);
+  assert_eq!(tokens[9].node, End);
+  assert_eq!(tokens[10].node, EOF);
+}
+
 #[test]
 fn lex_structure() {
   // this test uses a single trailing hash to avoid trailing whitespace errors in git and editors
@@ -249,3 +434,70 @@ fn lex_trailing_exits() {
   assert_eq!(tokens[6].node, End);
   assert_eq!(tokens[7].node, EOF);
 }
+
+#[test]
+fn lex_categories() {
+  let mut map = CodeMap::new();
+  let file = map.add_file(String::from("_test"), String::from("fn if + * 42 's' x ( :"));
+  let tokens = lex_with_categories(&file).unwrap();
+  assert_eq!(tokens.len(), 11);
+  assert_eq!(tokens[0].1, TokenCategory::Keyword);
+  assert_eq!(tokens[1].1, TokenCategory::Keyword);
+  assert_eq!(tokens[2].1, TokenCategory::Operator);
+  assert_eq!(tokens[3].1, TokenCategory::Operator);
+  assert_eq!(tokens[4].1, TokenCategory::Literal);
+  assert_eq!(tokens[5].1, TokenCategory::Literal);
+  assert_eq!(tokens[6].1, TokenCategory::Identifier);
+  assert_eq!(tokens[7].1, TokenCategory::Brace);
+  assert_eq!(tokens[8].1, TokenCategory::Symbol);
+  assert_eq!(tokens[9].1, TokenCategory::Structure);
+  assert_eq!(tokens[10].1, TokenCategory::Structure);
+
+  assert_eq!(tokens[4].0.node, Int(42));
+  assert_eq!(file.source_slice(tokens[5].0.span), "'s'");
+}
+
+#[test]
+fn lex_streaming() {
+  let source = "fn(x)
+  if x > 0
+    return 'pos'
+  while x
+    x = x - 1.5 # comment
+
+    if x
+      pass
+";
+  let mut map = CodeMap::new();
+  let file = map.add_file(String::from("_test"), String::from(source));
+
+  let streamed: Vec<Spanned<Token>> = Tokens::new(&file).map(|tok| tok.unwrap()).collect();
+  assert_eq!(streamed, lex(&file).unwrap());
+
+  let nodes: Vec<Token> = streamed.into_iter().map(|tok| tok.node).collect();
+  assert_eq!(
+    nodes,
+    vec![
+      Func, Pal, Name(String::from("x")), Par, Enter,
+      If, Name(String::from("x")), Gt, Int(0), Enter,
+      Return, Str(String::from("pos")), End,
+      Exit, End,
+      While, Name(String::from("x")), Enter,
+      Name(String::from("x")), Ass, Name(String::from("x")), Sub, Float(1.5), End,
+      If, Name(String::from("x")), Enter,
+      Pass, End,
+      Exit, End,
+      Exit, End,
+      Exit, End,
+      EOF,
+    ]
+  );
+
+  // errors stop the stream
+  let file = map.add_file(String::from("_test"), String::from("x = 'oops"));
+  let mut tokens = Tokens::new(&file);
+  assert_eq!(tokens.next().map(|tok| tok.unwrap().node), Some(Name(String::from("x"))));
+  assert_eq!(tokens.next().map(|tok| tok.unwrap().node), Some(Ass));
+  assert_eq!(tokens.next().map(|tok| tok.unwrap_err().node), Some(LexErrorKind::UnclosedStr));
+  assert_eq!(tokens.next(), None);
+}
@@ -8,12 +8,20 @@ use std::cmp::PartialEq;
 fn get_tokens(source: &str) -> Vec<Spanned<Token>> {
   let mut map = CodeMap::new();
   let file = map.add_file(String::from("_test"), String::from(source));
-  lexer::lex(&file)
+  lexer::lex(&file).unwrap()
+}
+
+// Parsers either report the exact token they stopped at, or what they wanted instead of it
+fn assert_stops_at<T: Debug>(res: Result<T, ParseErrorKind>, tok: lexer::Token) {
+  match res {
+    Err(UnexpectedToken(ref found)) | Err(Expected { ref found, .. }) if *found == tok => {}
+    _ => panic!("expected to stop at {:?}, got {:?}", tok, res),
+  }
 }
 
 fn test_parse<T: Debug + PartialEq>(
   source: &str,
-  func: &Fn(&mut ParseIter) -> Result<T, ParseErrorKind>,
+  func: &dyn Fn(&mut ParseIter) -> Result<T, ParseErrorKind>,
   expect: Result<T, ParseErrorKind>,
 ) {
   let tokens = get_tokens(source);
@@ -21,10 +29,10 @@ fn test_parse<T: Debug + PartialEq>(
 
   assert_eq!(func(&mut it), expect);
 
-  assert_eq!(func(&mut it), Err(UnexpectedToken(lexer::Token::End)));
+  assert_stops_at(func(&mut it), lexer::Token::End);
   it.next();
 
-  assert_eq!(func(&mut it), Err(UnexpectedToken(lexer::Token::EOF)));
+  assert_stops_at(func(&mut it), lexer::Token::EOF);
   it.next();
 
   assert_eq!(func(&mut it), Err(UnexpectedEOF));
@@ -192,6 +200,64 @@ fn test_un_expr() {
   );
 }
 
+// This only pins the tree shape of a long chain. It can't tell whether
+// parse_bin_expr clones along the way, since a cloning parser builds the
+// same tree.
+#[test]
+fn test_long_bin_expr() {
+  let source = vec!["a"; 1000].join(" + ");
+
+  let mut expected = Node::Name(String::from("a"));
+  for _ in 1..1000 {
+    expected = Node::BinExpr {
+      lhs: Box::new(expected),
+      op: lexer::Token::Add,
+      rhs: Box::new(Node::Name(String::from("a"))),
+    };
+  }
+
+  test_parse(&source, &parse_bin_expr, Ok(expected));
+}
+
+#[test]
+fn test_nested_precedence() {
+  test_parse(
+    "1 | 2 + 3 * 4",
+    &parse_bin_expr,
+    Ok(Node::BinExpr {
+      lhs: Box::new(Node::Int(1)),
+      op: lexer::Token::Or,
+      rhs: Box::new(Node::BinExpr {
+        lhs: Box::new(Node::Int(2)),
+        op: lexer::Token::Add,
+        rhs: Box::new(Node::BinExpr {
+          lhs: Box::new(Node::Int(3)),
+          op: lexer::Token::Mul,
+          rhs: Box::new(Node::Int(4)),
+        }),
+      }),
+    }),
+  );
+
+  test_parse(
+    "1 * 2 + 3 | 4",
+    &parse_bin_expr,
+    Ok(Node::BinExpr {
+      lhs: Box::new(Node::BinExpr {
+        lhs: Box::new(Node::BinExpr {
+          lhs: Box::new(Node::Int(1)),
+          op: lexer::Token::Mul,
+          rhs: Box::new(Node::Int(2)),
+        }),
+        op: lexer::Token::Add,
+        rhs: Box::new(Node::Int(3)),
+      }),
+      op: lexer::Token::Or,
+      rhs: Box::new(Node::Int(4)),
+    }),
+  );
+}
+
 #[test]
 fn test_bin_expr() {
   test_parse(
@@ -229,6 +295,267 @@ fn test_bin_expr() {
   );
 }
 
+#[test]
+fn test_bitwise_expr() {
+  test_parse(
+    "a & b | c",
+    &parse_bin_expr,
+    Ok(Node::BinExpr {
+      lhs: Box::new(Node::BinExpr {
+        lhs: Box::new(Node::Name(String::from("a"))),
+        op: lexer::Token::And,
+        rhs: Box::new(Node::Name(String::from("b"))),
+      }),
+      op: lexer::Token::Or,
+      rhs: Box::new(Node::Name(String::from("c"))),
+    }),
+  );
+
+  test_parse(
+    "a | b & c",
+    &parse_bin_expr,
+    Ok(Node::BinExpr {
+      lhs: Box::new(Node::Name(String::from("a"))),
+      op: lexer::Token::Or,
+      rhs: Box::new(Node::BinExpr {
+        lhs: Box::new(Node::Name(String::from("b"))),
+        op: lexer::Token::And,
+        rhs: Box::new(Node::Name(String::from("c"))),
+      }),
+    }),
+  );
+
+  test_parse(
+    "a << 2 + 1",
+    &parse_bin_expr,
+    Ok(Node::BinExpr {
+      lhs: Box::new(Node::Name(String::from("a"))),
+      op: lexer::Token::Shl,
+      rhs: Box::new(Node::BinExpr {
+        lhs: Box::new(Node::Int(2)),
+        op: lexer::Token::Add,
+        rhs: Box::new(Node::Int(1)),
+      }),
+    }),
+  );
+
+  test_parse(
+    "a >> 1 & b",
+    &parse_bin_expr,
+    Ok(Node::BinExpr {
+      lhs: Box::new(Node::BinExpr {
+        lhs: Box::new(Node::Name(String::from("a"))),
+        op: lexer::Token::Shr,
+        rhs: Box::new(Node::Int(1)),
+      }),
+      op: lexer::Token::And,
+      rhs: Box::new(Node::Name(String::from("b"))),
+    }),
+  );
+
+  test_parse(
+    "~x",
+    &parse_bin_expr,
+    Ok(Node::UnExpr {
+      op: lexer::Token::Neg,
+      val: Box::new(Node::Name(String::from("x"))),
+    }),
+  );
+
+  test_parse(
+    "~x & 255",
+    &parse_bin_expr,
+    Ok(Node::BinExpr {
+      lhs: Box::new(Node::UnExpr {
+        op: lexer::Token::Neg,
+        val: Box::new(Node::Name(String::from("x"))),
+      }),
+      op: lexer::Token::And,
+      rhs: Box::new(Node::Int(255)),
+    }),
+  );
+}
+
+#[test]
+fn test_word_operators() {
+  test_parse(
+    "a and not b",
+    &parse_bin_expr,
+    Ok(Node::BinExpr {
+      lhs: Box::new(Node::Name(String::from("a"))),
+      op: lexer::Token::LogAnd,
+      rhs: Box::new(Node::UnExpr {
+        op: lexer::Token::Not,
+        val: Box::new(Node::Name(String::from("b"))),
+      }),
+    }),
+  );
+
+  test_parse(
+    "a or b and c",
+    &parse_bin_expr,
+    Ok(Node::BinExpr {
+      lhs: Box::new(Node::Name(String::from("a"))),
+      op: lexer::Token::LogOr,
+      rhs: Box::new(Node::BinExpr {
+        lhs: Box::new(Node::Name(String::from("b"))),
+        op: lexer::Token::LogAnd,
+        rhs: Box::new(Node::Name(String::from("c"))),
+      }),
+    }),
+  );
+
+  test_parse(
+    "a | b and c",
+    &parse_bin_expr,
+    Ok(Node::BinExpr {
+      lhs: Box::new(Node::BinExpr {
+        lhs: Box::new(Node::Name(String::from("a"))),
+        op: lexer::Token::Or,
+        rhs: Box::new(Node::Name(String::from("b"))),
+      }),
+      op: lexer::Token::LogAnd,
+      rhs: Box::new(Node::Name(String::from("c"))),
+    }),
+  );
+
+  assert_eq!(parse_un_expr_str("not x"), parse_un_expr_str("!x"));
+
+  assert_eq!(
+    parse(get_tokens("and = 1")),
+    Err(ReservedWord(lexer::Token::LogAnd))
+  );
+  assert_eq!(
+    parse(get_tokens("x = or")),
+    Err(ReservedWord(lexer::Token::LogOr))
+  );
+  assert_eq!(
+    parse(get_tokens("not = 1")),
+    Err(ReservedWord(lexer::Token::LogNot))
+  );
+  assert_eq!(
+    parse(get_tokens("[a, not] = x")),
+    Err(ReservedWord(lexer::Token::LogNot))
+  );
+  assert_eq!(
+    parse(get_tokens("x = not")),
+    Err(ReservedWord(lexer::Token::LogNot))
+  );
+  assert_eq!(
+    parse(get_tokens("x = not + 1")),
+    Err(ReservedWord(lexer::Token::LogNot))
+  );
+  assert_eq!(
+    parse(get_tokens("x = not if c")),
+    Err(ReservedWord(lexer::Token::LogNot))
+  );
+  assert_eq!(
+    parse(get_tokens("x = not (")),
+    Err(UnexpectedToken(lexer::Token::End))
+  );
+}
+
+fn parse_un_expr_str(source: &str) -> Parse {
+  let tokens = get_tokens(source);
+  let mut it = tokens.iter().peekable();
+  parse_un_expr(&mut it)
+}
+
+#[test]
+fn test_pipe() {
+  test_parse(
+    "x |> f",
+    &parse_bin_expr,
+    Ok(Node::Call {
+      func: Box::new(Node::Name(String::from("f"))),
+      args: vec![Node::Name(String::from("x"))],
+    }),
+  );
+
+  test_parse(
+    "x |> f(y)",
+    &parse_bin_expr,
+    Ok(Node::Call {
+      func: Box::new(Node::Name(String::from("f"))),
+      args: vec![Node::Name(String::from("x")), Node::Name(String::from("y"))],
+    }),
+  );
+
+  test_parse(
+    "x |> obj:m(y)",
+    &parse_bin_expr,
+    Ok(Node::Method {
+      owner: Box::new(Node::Name(String::from("obj"))),
+      method: Box::new(Node::Str(String::from("m"))),
+      args: vec![Node::Name(String::from("x")), Node::Name(String::from("y"))],
+    }),
+  );
+
+  test_parse(
+    "a + 1 |> f |> g(2)",
+    &parse_bin_expr,
+    Ok(Node::Call {
+      func: Box::new(Node::Name(String::from("g"))),
+      args: vec![
+        Node::Call {
+          func: Box::new(Node::Name(String::from("f"))),
+          args: vec![Node::BinExpr {
+            lhs: Box::new(Node::Name(String::from("a"))),
+            op: lexer::Token::Add,
+            rhs: Box::new(Node::Int(1)),
+          }],
+        },
+        Node::Int(2),
+      ],
+    }),
+  );
+}
+
+#[test]
+fn test_loop_levels() {
+  test_parse("break", &parse_stmt, Ok(Node::Break(1)));
+  test_parse("break 2", &parse_stmt, Ok(Node::Break(2)));
+  test_parse("continue 3", &parse_stmt, Ok(Node::Continue(3)));
+
+  test_parse(
+    "continue 2 if x",
+    &parse_stmt,
+    Ok(Node::If {
+      cond: Box::new(Node::Name(String::from("x"))),
+      body: vec![Node::Continue(2)],
+      els: None,
+    }),
+  );
+
+  assert_eq!(
+    parse(get_tokens("break 0")),
+    Err(UnexpectedToken(lexer::Token::Int(0)))
+  );
+}
+
+#[test]
+fn test_discard_params() {
+  test_parse(
+    "|_| 1",
+    &parse_il_expr,
+    Ok(Node::Lambda {
+      params: vec![String::from("_")],
+      expr: Box::new(Node::Int(1)),
+    }),
+  );
+
+  assert_eq!(
+    parse(get_tokens("f = fn(_, x)\n  return x")),
+    Ok(Node::Block(vec![Node::Assn {
+      lhs: Place::Single(Box::new(Node::Name(String::from("f")))),
+      rhs: Box::new(Node::Func {
+        params: vec![String::from("_"), String::from("x")],
+        body: vec![Node::Return(Some(Box::new(Node::Name(String::from("x")))))],
+      }),
+    }]))
+  );
+}
+
 #[test]
 fn test_fn_expr() {
   test_parse(
@@ -334,6 +661,85 @@ fn test_decl() {
   );
 }
 
+#[test]
+fn test_decl_defaults() {
+  test_parse(
+    "[a, b = 0]",
+    &parse_decl,
+    Ok(Var::Multi(vec![
+      Var::Single(String::from("a")),
+      Var::Default(
+        Box::new(Var::Single(String::from("b"))),
+        Box::new(Node::Int(0)),
+      ),
+    ])),
+  );
+
+  test_parse(
+    "[a, [b, c] = d, e = 1 + 2]",
+    &parse_decl,
+    Ok(Var::Multi(vec![
+      Var::Single(String::from("a")),
+      Var::Default(
+        Box::new(Var::Multi(vec![
+          Var::Single(String::from("b")),
+          Var::Single(String::from("c")),
+        ])),
+        Box::new(Node::Name(String::from("d"))),
+      ),
+      Var::Default(
+        Box::new(Var::Single(String::from("e"))),
+        Box::new(Node::BinExpr {
+          lhs: Box::new(Node::Int(1)),
+          op: lexer::Token::Add,
+          rhs: Box::new(Node::Int(2)),
+        }),
+      ),
+    ])),
+  );
+
+  let tokens = get_tokens("[a = 0, b]");
+  let mut it = tokens.iter().peekable();
+  assert_eq!(parse_decl(&mut it), Err(NonTrailingDefault));
+}
+
+#[test]
+fn test_discard() {
+  test_parse(
+    "[_, x, _]",
+    &parse_place,
+    Ok(Place::Multi(vec![
+      Place::Single(Box::new(Node::Discard)),
+      Place::Single(Box::new(Node::Name(String::from("x")))),
+      Place::Single(Box::new(Node::Discard)),
+    ])),
+  );
+
+  test_parse(
+    "[_, [y, _]]",
+    &parse_decl,
+    Ok(Var::Multi(vec![
+      Var::Discard,
+      Var::Multi(vec![Var::Single(String::from("y")), Var::Discard]),
+    ])),
+  );
+
+  test_parse(
+    "_ = f()",
+    &parse_stmt,
+    Ok(Node::Assn {
+      lhs: Place::Single(Box::new(Node::Discard)),
+      rhs: Box::new(Node::Call {
+        func: Box::new(Node::Name(String::from("f"))),
+        args: Vec::new(),
+      }),
+    }),
+  );
+
+  assert_eq!(parse(get_tokens("_")), Err(UnusedPlaces));
+  assert_eq!(parse(get_tokens("x = _")), Err(UnexpectedToken(lexer::Token::Und)));
+}
+
 #[test]
 fn test_return_stmt() {
   test_parse("return", &parse_stmt, Ok(Node::Return(None)));
@@ -353,6 +759,31 @@ fn test_return_stmt() {
       body: vec![Node::Return(Some(Box::new(Node::Int(5))))],
     })))),
   );
+  test_parse(
+    "return a, b",
+    &parse_stmt,
+    Ok(Node::Return(Some(Box::new(Node::Tuple(vec![
+      Node::Name(String::from("a")),
+      Node::Name(String::from("b")),
+    ]))))),
+  );
+
+  test_parse(
+    "return a, b + 1, c()",
+    &parse_stmt,
+    Ok(Node::Return(Some(Box::new(Node::Tuple(vec![
+      Node::Name(String::from("a")),
+      Node::BinExpr {
+        lhs: Box::new(Node::Name(String::from("b"))),
+        op: lexer::Token::Add,
+        rhs: Box::new(Node::Int(1)),
+      },
+      Node::Call {
+        func: Box::new(Node::Name(String::from("c"))),
+        args: Vec::new(),
+      },
+    ]))))),
+  );
 }
 
 #[test]
@@ -378,6 +809,16 @@ fn test_if_stmt() {
     }),
   );
 
+  test_parse(
+    "elif true
+       pass",
+    &parse_stmt,
+    Ok(Node::ElseIf {
+      cond: Box::new(Node::Bool(true)),
+      body: vec![Node::Pass],
+    }),
+  );
+
   test_parse(
     "else
        pass",
@@ -402,6 +843,26 @@ fn test_for_stmt() {
   );
 }
 
+#[test]
+fn test_for_stmt_defaults() {
+  test_parse(
+    "for [k, v = null] in pairs
+       pass",
+    &parse_stmt,
+    Ok(Node::For {
+      decl: Var::Multi(vec![
+        Var::Single(String::from("k")),
+        Var::Default(
+          Box::new(Var::Single(String::from("v"))),
+          Box::new(Node::Null),
+        ),
+      ]),
+      expr: Box::new(Node::Name(String::from("pairs"))),
+      body: vec![Node::Pass],
+    }),
+  );
+}
+
 #[test]
 fn test_while_stmt() {
   test_parse(
@@ -427,6 +888,49 @@ fn test_loop_stmt() {
   );
 }
 
+#[test]
+fn test_place_defaults() {
+  let ast = parse(get_tokens(
+    "src = table
+src[0] = 1
+[a, b = 0] = src",
+  ));
+
+  let unpack = Node::Assn {
+    lhs: Place::Multi(vec![
+      Place::Single(Box::new(Node::Name(String::from("a")))),
+      Place::Default(
+        Box::new(Place::Single(Box::new(Node::Name(String::from("b"))))),
+        Box::new(Node::Int(0)),
+      ),
+    ]),
+    rhs: Box::new(Node::Name(String::from("src"))),
+  };
+
+  match ast {
+    Ok(Node::Block(ref stmts)) => assert_eq!(stmts[2], unpack),
+    _ => panic!("bad parse: {:?}", ast),
+  }
+
+  test_parse(
+    "[a, [b, c] = x, _ = 1]",
+    &parse_place,
+    Ok(Place::Multi(vec![
+      Place::Single(Box::new(Node::Name(String::from("a")))),
+      Place::Default(
+        Box::new(Place::Multi(vec![
+          Place::Single(Box::new(Node::Name(String::from("b")))),
+          Place::Single(Box::new(Node::Name(String::from("c")))),
+        ])),
+        Box::new(Node::Name(String::from("x"))),
+      ),
+      Place::Default(Box::new(Place::Single(Box::new(Node::Discard))), Box::new(Node::Int(1))),
+    ])),
+  );
+
+  assert_eq!(parse(get_tokens("[a = 0, b] = src")), Err(NonTrailingDefault));
+}
+
 #[test]
 fn test_place() {
   test_parse(
@@ -509,3 +1013,378 @@ fn test_place() {
     ])),
   );
 }
+
+struct IntCounter {
+  count: usize,
+}
+
+impl Visitor for IntCounter {
+  type Error = ();
+
+  fn visit(&mut self, node: &Node) -> Result<(), ()> {
+    if let Node::Int(_) = *node {
+      self.count += 1;
+    }
+    self.walk_node(node)
+  }
+}
+
+#[test]
+fn test_visitor() {
+  let tokens = get_tokens(
+    "x = 1 + 2 * y
+if 3
+  [a, b[4]] = f(5, |z| 6)
+else
+  loop
+    return 7",
+  );
+  let root = parse(tokens).unwrap();
+
+  let mut counter = IntCounter { count: 0 };
+  assert_eq!(counter.visit(&root), Ok(()));
+  assert_eq!(counter.count, 7);
+}
+
+#[test]
+fn test_elif_matches_else_if() {
+  let elif = parse(get_tokens(
+    "if a
+  x = 1
+elif b + 1
+  x = 2
+else
+  x = 3",
+  ));
+  let else_if = parse(get_tokens(
+    "if a
+  x = 1
+else if b + 1
+  x = 2
+else
+  x = 3",
+  ));
+
+  assert!(elif.is_ok());
+  assert_eq!(elif, else_if);
+}
+
+#[test]
+fn test_expected_token() {
+  assert_eq!(
+    parse(get_tokens("(1 + 2")),
+    Err(Expected {
+      expected: vec![lexer::Token::Par],
+      found: lexer::Token::End,
+    })
+  );
+
+  assert_eq!(
+    parse(get_tokens("for x y")),
+    Err(Expected {
+      expected: vec![lexer::Token::In],
+      found: lexer::Token::Name(String::from("y")),
+    })
+  );
+
+  assert_eq!(
+    parse(get_tokens("foo(1")),
+    Err(Expected {
+      expected: vec![lexer::Token::Par],
+      found: lexer::Token::End,
+    })
+  );
+}
+
+#[test]
+fn test_guard_stmt() {
+  test_parse(
+    "return x if c",
+    &parse_stmt,
+    Ok(Node::If {
+      cond: Box::new(Node::Name(String::from("c"))),
+      body: vec![Node::Return(Some(Box::new(Node::Name(String::from("x")))))],
+      els: None,
+    }),
+  );
+
+  test_parse(
+    "return if c",
+    &parse_stmt,
+    Ok(Node::If {
+      cond: Box::new(Node::Name(String::from("c"))),
+      body: vec![Node::Return(None)],
+      els: None,
+    }),
+  );
+
+  test_parse(
+    "break if !done",
+    &parse_stmt,
+    Ok(Node::If {
+      cond: Box::new(Node::UnExpr {
+        op: lexer::Token::Not,
+        val: Box::new(Node::Name(String::from("done"))),
+      }),
+      body: vec![Node::Break(1)],
+      els: None,
+    }),
+  );
+
+  test_parse(
+    "x = 1 if c",
+    &parse_stmt,
+    Ok(Node::If {
+      cond: Box::new(Node::Name(String::from("c"))),
+      body: vec![Node::Assn {
+        lhs: Place::Single(Box::new(Node::Name(String::from("x")))),
+        rhs: Box::new(Node::Int(1)),
+      }],
+      els: None,
+    }),
+  );
+
+  test_parse(
+    "f() if c",
+    &parse_stmt,
+    Ok(Node::If {
+      cond: Box::new(Node::Name(String::from("c"))),
+      body: vec![Node::Stmt(Box::new(Node::Call {
+        func: Box::new(Node::Name(String::from("f"))),
+        args: Vec::new(),
+      }))],
+      els: None,
+    }),
+  );
+
+  test_parse(
+    "if c
+       return x",
+    &parse_stmt,
+    Ok(Node::If {
+      cond: Box::new(Node::Name(String::from("c"))),
+      body: vec![Node::Return(Some(Box::new(Node::Name(String::from("x")))))],
+      els: None,
+    }),
+  );
+}
+
+#[test]
+fn test_catch_expr() {
+  test_parse(
+    "catch
+       pass",
+    &parse_ml_expr,
+    Ok(Node::Catch {
+      body: vec![Node::Pass],
+      finally: None,
+    }),
+  );
+
+  assert_eq!(
+    parse(get_tokens(
+      "err = catch
+  risky()
+finally
+  cleanup()
+done()"
+    )),
+    Ok(Node::Block(vec![
+      Node::Assn {
+        lhs: Place::Single(Box::new(Node::Name(String::from("err")))),
+        rhs: Box::new(Node::Catch {
+          body: vec![Node::Stmt(Box::new(Node::Call {
+            func: Box::new(Node::Name(String::from("risky"))),
+            args: Vec::new(),
+          }))],
+          finally: Some(vec![Node::Stmt(Box::new(Node::Call {
+            func: Box::new(Node::Name(String::from("cleanup"))),
+            args: Vec::new(),
+          }))]),
+        }),
+      },
+      Node::Stmt(Box::new(Node::Call {
+        func: Box::new(Node::Name(String::from("done"))),
+        args: Vec::new(),
+      })),
+    ]))
+  );
+
+  assert_eq!(
+    parse(get_tokens(
+      "catch
+  pass
+done()"
+    )),
+    Ok(Node::Block(vec![
+      Node::Stmt(Box::new(Node::Catch {
+        body: vec![Node::Pass],
+        finally: None,
+      })),
+      Node::Stmt(Box::new(Node::Call {
+        func: Box::new(Node::Name(String::from("done"))),
+        args: Vec::new(),
+      })),
+    ]))
+  );
+
+  // a finally with no catch to attach to is just an error
+  assert!(parse(get_tokens(
+    "finally
+  pass"
+  ))
+  .is_err());
+}
+
+#[test]
+fn test_diff() {
+  let a = parse(get_tokens("x = 1\ny = 2\nif a\n  z = 1")).unwrap();
+  let b = parse(get_tokens("x = 1\ny = 2\nif a\n  z = 1")).unwrap();
+  assert_eq!(diff(&a, &b), None);
+
+  let c = parse(get_tokens("x = 1\ny = 2\nif a\n  z = 2")).unwrap();
+  assert_eq!(
+    diff(&a, &c),
+    Some(String::from("at Block[2].If.body[0].Assn.rhs: expected Int(1), found Int(2)"))
+  );
+
+  let d = parse(get_tokens("x = 1\ny = 2\nif b\n  z = 1")).unwrap();
+  assert_eq!(
+    diff(&a, &d),
+    Some(String::from(
+      "at Block[2].If.cond: expected Name(\"a\"), found Name(\"b\")"
+    ))
+  );
+
+  let e = parse(get_tokens("x = 1\ny = 2")).unwrap();
+  assert_eq!(
+    diff(&a, &e),
+    Some(String::from("at Block: expected 3 nodes, found 2"))
+  );
+
+  assert_eq!(
+    diff(&Node::Int(1), &Node::Null),
+    Some(String::from("expected Int(1), found Null"))
+  );
+}
+
+#[test]
+fn test_method_chains() {
+  let name = |x: &str| Box::new(Node::Name(String::from(x)));
+  let string = |x: &str| Box::new(Node::Str(String::from(x)));
+
+  test_parse(
+    "a:b():c()",
+    &parse_simple,
+    Ok(Node::Method {
+      owner: Box::new(Node::Method {
+        owner: name("a"),
+        method: string("b"),
+        args: vec![],
+      }),
+      method: string("c"),
+      args: vec![],
+    }),
+  );
+
+  test_parse(
+    "a().b:c()",
+    &parse_simple,
+    Ok(Node::Method {
+      owner: Box::new(Node::Index {
+        lhs: Box::new(Node::Call {
+          func: name("a"),
+          args: vec![],
+        }),
+        rhs: string("b"),
+      }),
+      method: string("c"),
+      args: vec![],
+    }),
+  );
+
+  test_parse(
+    "a[0]:b()",
+    &parse_simple,
+    Ok(Node::Method {
+      owner: Box::new(Node::Index {
+        lhs: name("a"),
+        rhs: Box::new(Node::Int(0)),
+      }),
+      method: string("b"),
+      args: vec![],
+    }),
+  );
+
+  test_parse(
+    "obj:method().field:another(x)",
+    &parse_simple,
+    Ok(Node::Method {
+      owner: Box::new(Node::Index {
+        lhs: Box::new(Node::Method {
+          owner: name("obj"),
+          method: string("method"),
+          args: vec![],
+        }),
+        rhs: string("field"),
+      }),
+      method: string("another"),
+      args: vec![Node::Name(String::from("x"))],
+    }),
+  );
+
+  test_parse(
+    "f()(x)",
+    &parse_simple,
+    Ok(Node::Call {
+      func: Box::new(Node::Call {
+        func: name("f"),
+        args: vec![],
+      }),
+      args: vec![Node::Name(String::from("x"))],
+    }),
+  );
+}
+
+#[test]
+fn test_unless_until() {
+  test_parse(
+    "unless c\n  pass",
+    &parse_stmt,
+    Ok(Node::If {
+      cond: Box::new(Node::UnExpr {
+        op: lexer::Token::Not,
+        val: Box::new(Node::Name(String::from("c"))),
+      }),
+      body: vec![Node::Pass],
+      els: None,
+    }),
+  );
+
+  test_parse(
+    "until c\n  pass",
+    &parse_stmt,
+    Ok(Node::While {
+      expr: Box::new(Node::UnExpr {
+        op: lexer::Token::Not,
+        val: Box::new(Node::Name(String::from("c"))),
+      }),
+      body: vec![Node::Pass],
+    }),
+  );
+
+  assert_eq!(
+    parse(get_tokens(
+      "unless a
+  x = 1
+else
+  x = 2",
+    )),
+    parse(get_tokens(
+      "if !a
+  x = 1
+else
+  x = 2",
+    ))
+  );
+}
@@ -0,0 +1,340 @@
+use super::*;
+use super::super::lexer;
+use super::super::parser;
+use codemap::CodeMap;
+
+fn check(source: &str) -> (Check, Vec<CheckErrorKind>) {
+  let mut map = CodeMap::new();
+  let file = map.add_file(String::from("_test"), String::from(source));
+  let root = parser::parse(lexer::lex(&file).unwrap()).unwrap();
+
+  let mut ck = SemChecker::new();
+  let res = ck.check(&root);
+  (res, ck.warnings().to_vec())
+}
+
+#[test]
+fn test_constant_condition() {
+  assert_eq!(
+    check(
+      "while 0
+  pass"
+    ),
+    (Ok(()), vec![CheckErrorKind::ConstantCondition])
+  );
+
+  assert_eq!(
+    check(
+      "while 1 + 2
+  pass"
+    ),
+    (Ok(()), vec![CheckErrorKind::ConstantCondition])
+  );
+
+  assert_eq!(
+    check(
+      "while true
+  pass"
+    ),
+    (Ok(()), vec![])
+  );
+
+  assert_eq!(
+    check(
+      "until false
+  pass"
+    ),
+    (Ok(()), vec![])
+  );
+
+  assert_eq!(
+    check(
+      "until true
+  pass"
+    ),
+    (Ok(()), vec![CheckErrorKind::ConstantCondition])
+  );
+
+  assert_eq!(
+    check(
+      "while x
+  pass"
+    ),
+    (Ok(()), vec![])
+  );
+}
+
+#[test]
+fn test_empty_loop() {
+  assert_eq!(
+    check(
+      "for x in table
+  pass"
+    ),
+    (Ok(()), vec![CheckErrorKind::EmptyLoop])
+  );
+
+  assert_eq!(
+    check(
+      "for x in y
+  pass"
+    ),
+    (Ok(()), vec![])
+  );
+}
+
+#[test]
+fn test_place() {
+  assert_eq!(check("x = 1").0, Ok(()));
+  assert_eq!(check("[a, b.c, d[0]] = x").0, Ok(()));
+  assert_eq!(check("[[a, [b, [c, d.e]]], f] = x").0, Ok(()));
+
+  assert_eq!(check("[a, 5] = x").0, Err(CheckErrorKind::NotPlace(Node::Int(5))));
+  assert_eq!(check("[a, b = 0] = x").0, Ok(()));
+  assert_eq!(
+    check("[a, 5 = 0] = x").0,
+    Err(CheckErrorKind::NotPlace(Node::Int(5)))
+  );
+  assert_eq!(
+    check("[[a, [b, [c, 'd']]], f] = x").0,
+    Err(CheckErrorKind::NotPlace(Node::Str(String::from("d"))))
+  );
+  assert_eq!(
+    check("[a, [f(), b]] = x").0,
+    Err(CheckErrorKind::NotPlace(Node::Call {
+      func: Box::new(Node::Name(String::from("f"))),
+      args: Vec::new(),
+    }))
+  );
+
+  // places nested inside other statements are checked too
+  assert_eq!(
+    check(
+      "if x
+  loop
+    [a, [b, null]] = y"
+    ).0,
+    Err(CheckErrorKind::NotPlace(Node::Null))
+  );
+}
+
+#[test]
+fn test_discard() {
+  assert_eq!(check("[_, x] = p").0, Ok(()));
+  assert_eq!(check("[_, [x, _]] = p").0, Ok(()));
+  assert_eq!(check("_ = f()").0, Ok(()));
+}
+
+#[test]
+fn test_finally() {
+  assert_eq!(
+    check(
+      "loop
+  catch
+    pass
+  finally
+    break"
+    ).0,
+    Ok(())
+  );
+
+  assert_eq!(
+    check(
+      "catch
+  pass
+finally
+  break"
+    ).0,
+    Err(CheckErrorKind::NotInLoop)
+  );
+}
+
+#[test]
+fn test_break_in_func() {
+  assert_eq!(
+    check(
+      "loop
+  f = fn()
+    break"
+    ).0,
+    Err(CheckErrorKind::NotInLoop)
+  );
+
+  assert_eq!(
+    check(
+      "while x
+  f = fn()
+    continue"
+    ).0,
+    Err(CheckErrorKind::NotInLoop)
+  );
+
+  assert_eq!(
+    check(
+      "for x in y
+  f = fn()
+    loop
+      continue
+  break"
+    ).0,
+    Ok(())
+  );
+}
+
+#[test]
+fn test_loop_levels() {
+  assert_eq!(
+    check(
+      "loop
+  while x
+    break 2
+  continue 1"
+    ).0,
+    Ok(())
+  );
+
+  assert_eq!(
+    check(
+      "loop
+  while x
+    break 3"
+    ).0,
+    Err(CheckErrorKind::LevelTooDeep(3))
+  );
+
+  assert_eq!(check("continue 2").0, Err(CheckErrorKind::NotInLoop));
+
+  assert_eq!(
+    check(
+      "while x
+  f = fn()
+    loop
+      break 2"
+    ).0,
+    Err(CheckErrorKind::LevelTooDeep(2))
+  );
+}
+
+#[test]
+fn test_shadowed() {
+  assert_eq!(
+    check(
+      "i = 0
+for i in x
+  pass"
+    ),
+    (Ok(()), vec![CheckErrorKind::Shadowed(String::from("i"))])
+  );
+
+  assert_eq!(
+    check(
+      "[k, v] = pair
+for [j, [_, v = 0]] in x
+  pass"
+    ),
+    (Ok(()), vec![CheckErrorKind::Shadowed(String::from("v"))])
+  );
+
+  assert_eq!(
+    check(
+      "for i in x
+  for i in y
+    pass"
+    ),
+    (Ok(()), vec![CheckErrorKind::Shadowed(String::from("i"))])
+  );
+
+  assert_eq!(
+    check(
+      "x = 1
+f = fn(x, y)
+  return |y| y"
+    ),
+    (
+      Ok(()),
+      vec![
+        CheckErrorKind::Shadowed(String::from("x")),
+        CheckErrorKind::Shadowed(String::from("y")),
+      ]
+    )
+  );
+}
+
+#[test]
+fn test_not_shadowed() {
+  assert_eq!(
+    check(
+      "f = fn(_, a)
+  return |_| a"
+    ),
+    (Ok(()), vec![])
+  );
+
+  assert_eq!(
+    check(
+      "for i in x
+  pass
+for i in y
+  j = i
+j = 0"
+    ),
+    (Ok(()), vec![])
+  );
+
+  assert_eq!(
+    check(
+      "f = fn(a)
+  b = a
+  return b
+g = fn(b)
+  return b"
+    ),
+    (Ok(()), vec![])
+  );
+}
+
+#[test]
+fn test_no_return() {
+  assert_eq!(
+    check(
+      "f = fn(x)
+  return x + 1"
+    ),
+    (Ok(()), vec![])
+  );
+
+  assert_eq!(
+    check(
+      "f = fn(x)
+  pass"
+    ),
+    (Ok(()), vec![CheckErrorKind::NoReturn])
+  );
+
+  assert_eq!(
+    check(
+      "f = fn(x)
+  if x
+    return"
+    ),
+    (Ok(()), vec![CheckErrorKind::NoReturn])
+  );
+
+  assert_eq!(
+    check(
+      "f = fn(x)
+  if x
+    return x"
+    ),
+    (Ok(()), vec![])
+  );
+
+  assert_eq!(
+    check(
+      "f = fn(x)
+  g = fn(y)
+    return y"
+    ),
+    (Ok(()), vec![CheckErrorKind::NoReturn])
+  );
+}